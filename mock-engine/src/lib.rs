@@ -0,0 +1,448 @@
+//! A local stand-in for the KittyCAD Modeling API's WebSocket.
+//!
+//! [`MockEngine`] listens on localhost and speaks the same [`WebSocketRequest`]/[`WebSocketResponse`]
+//! protocol as the real engine, so clients can be tested without an API token or a network connection.
+//! Tests can script its replies, inject failures and delays, and check which requests it received.
+//!
+//! Point a KittyCAD API client at it with `client.set_base_url(engine.base_url())`,
+//! or connect to [`MockEngine::ws_url`] with any WebSocket client.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{SinkExt, StreamExt};
+use kittycad_modeling_cmds::{
+    id::ModelingCmdId,
+    ok_response::{output, OkModelingCmdResponse},
+    websocket::{
+        ApiError, BatchResponse, ErrorCode, FailureWebSocketResponse, ModelingBatch, ModelingCmdReq,
+        ModelingSessionData, OkWebSocketResponseData, RawFile, SuccessWebSocketResponse, WebSocketRequest,
+        WebSocketResponse,
+    },
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{Request, Response},
+    Message as WsMsg,
+};
+
+/// How the mock engine should reply to a modeling command.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Reply successfully, with this response.
+    Success(OkModelingCmdResponse),
+    /// Reply that the command failed, with these errors.
+    Failure(Vec<ApiError>),
+    /// Reply with exported files, in a binary BSON message, like the engine does for `Export` commands.
+    Export(Vec<RawFile>),
+    /// Wait this long, then reply.
+    Delayed(Duration, Box<Reply>),
+    /// Never reply.
+    Ignore,
+    /// Close the WebSocket connection instead of replying.
+    Disconnect,
+}
+
+impl Reply {
+    /// Reply that the command failed, with a single error.
+    pub fn error(error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::Failure(vec![ApiError {
+            error_code,
+            message: message.into(),
+        }])
+    }
+
+    /// Wait this long before replying.
+    pub fn after(self, delay: Duration) -> Self {
+        Self::Delayed(delay, Box::new(self))
+    }
+
+    /// Split this reply into how long to wait, and what to reply after waiting.
+    fn without_delays(self) -> (Duration, Self) {
+        let mut delay = Duration::ZERO;
+        let mut reply = self;
+        while let Self::Delayed(d, inner) = reply {
+            delay += d;
+            reply = *inner;
+        }
+        (delay, reply)
+    }
+}
+
+impl Default for Reply {
+    /// Succeed, with an empty response.
+    fn default() -> Self {
+        Self::Success(OkModelingCmdResponse::Empty)
+    }
+}
+
+impl From<OkModelingCmdResponse> for Reply {
+    fn from(resp: OkModelingCmdResponse) -> Self {
+        Self::Success(resp)
+    }
+}
+
+type Handler = Box<dyn Fn(&ModelingCmdReq) -> Reply + Send + Sync>;
+
+/// Everything the mock engine has been told to do, and everything it has seen.
+#[derive(Default)]
+struct State {
+    /// Replies to use for the next modeling commands, in order.
+    queued: VecDeque<Reply>,
+    /// Decides the reply to any command which doesn't have a queued reply.
+    handler: Option<Handler>,
+    /// Every request received, over any connection, in order.
+    received: Vec<WebSocketRequest>,
+    /// How many WebSocket connections have been accepted.
+    connections: usize,
+    /// URI (path and query) which each connection was opened with, in order.
+    uris: Vec<String>,
+    /// Set to stop answering pings, like an engine which has hung.
+    ignore_pings: bool,
+    /// If set, modeling commands fail unless their connection sent this token in a `Headers` request.
+    required_token: Option<String>,
+    /// Each open connection, by the order it was accepted in.
+    open: HashMap<usize, mpsc::UnboundedSender<Outgoing>>,
+}
+
+impl State {
+    fn reply_to(&mut self, cmd: &ModelingCmdReq, token: Option<&str>) -> Reply {
+        match (&self.required_token, token) {
+            (Some(_), None) => return Reply::error(ErrorCode::AuthTokenMissing, "no auth token"),
+            (Some(required), Some(token)) if required != token => {
+                return Reply::error(ErrorCode::AuthTokenInvalid, "wrong auth token")
+            }
+            _ => {}
+        }
+        if let Some(reply) = self.queued.pop_front() {
+            return reply;
+        }
+        match &self.handler {
+            Some(handler) => handler(cmd),
+            None => Reply::default(),
+        }
+    }
+}
+
+/// Messages for a connection's writer.
+enum Outgoing {
+    Msg(WsMsg),
+    Close,
+}
+
+/// A local WebSocket server which pretends to be the KittyCAD Modeling API.
+/// It stops when dropped.
+pub struct MockEngine {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+impl MockEngine {
+    /// Start listening on a free localhost port.
+    /// By default, every modeling command succeeds with an empty response.
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let server = tokio::task::spawn(accept_connections(listener, state.clone()));
+        Ok(Self { addr, state, server })
+    }
+
+    /// Base URL for a KittyCAD API client to reach this engine.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// URL for a WebSocket client to connect to this engine directly.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Decide how to reply to each modeling command, unless it has a queued reply.
+    /// Replaces any previous handler.
+    pub fn handle<F>(&self, handler: F)
+    where
+        F: Fn(&ModelingCmdReq) -> Reply + Send + Sync + 'static,
+    {
+        self.state.lock().unwrap().handler = Some(Box::new(handler));
+    }
+
+    /// Use this reply for the next modeling command received. Queued replies are used in the
+    /// order they were queued, before falling back to the handler.
+    pub fn queue_reply(&self, reply: impl Into<Reply>) {
+        self.state.lock().unwrap().queued.push_back(reply.into());
+    }
+
+    /// Should the engine answer pings? It does by default.
+    pub fn respond_to_pings(&self, respond: bool) {
+        self.state.lock().unwrap().ignore_pings = !respond;
+    }
+
+    /// Only run modeling commands on connections which sent this token in a
+    /// [`WebSocketRequest::Headers`] request, as `Authorization: Bearer <token>`.
+    /// Other commands fail with [`ErrorCode::AuthTokenMissing`] or [`ErrorCode::AuthTokenInvalid`].
+    /// Replaces any previously required token, like the API does when a token expires.
+    pub fn require_token(&self, token: impl Into<String>) {
+        self.state.lock().unwrap().required_token = Some(token.into());
+    }
+
+    /// Every request received so far, over any connection, in order.
+    pub fn received(&self) -> Vec<WebSocketRequest> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Every modeling command received so far, including commands sent in batches, in order.
+    pub fn received_cmds(&self) -> Vec<ModelingCmdReq> {
+        self.state
+            .lock()
+            .unwrap()
+            .received
+            .iter()
+            .flat_map(|req| match req {
+                WebSocketRequest::ModelingCmdReq(cmd) => vec![cmd.clone()],
+                WebSocketRequest::ModelingCmdBatchReq(batch) => batch.requests.clone(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// How many WebSocket connections have been accepted so far.
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    /// URI (path and query) which each connection was opened with, in order.
+    /// The query has the client's engine parameters, like its video resolution.
+    pub fn connection_uris(&self) -> Vec<String> {
+        self.state.lock().unwrap().uris.clone()
+    }
+
+    /// Send a message to every open connection, without a request ID, like the engine does when
+    /// it wants something from the client or has something to tell it.
+    pub fn push_event(&self, resp: OkWebSocketResponseData) {
+        let msg = WebSocketResponse::Success(SuccessWebSocketResponse {
+            success: true,
+            request_id: None,
+            resp,
+        });
+        for conn in self.state.lock().unwrap().open.values() {
+            let _ = conn.send(Outgoing::Msg(to_ws(msg.clone())));
+        }
+    }
+
+    /// Close every open WebSocket connection, like the engine restarting.
+    /// New connections will still be accepted.
+    pub fn disconnect_all(&self) {
+        for (_, conn) in self.state.lock().unwrap().open.drain() {
+            let _ = conn.send(Outgoing::Close);
+        }
+    }
+}
+
+impl Drop for MockEngine {
+    fn drop(&mut self) {
+        self.disconnect_all();
+        self.server.abort();
+    }
+}
+
+async fn accept_connections(listener: TcpListener, state: Arc<Mutex<State>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::task::spawn(serve(stream, state.clone()));
+    }
+}
+
+/// Reply to every request on one WebSocket connection, until it closes.
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    // Clients might connect to any path, with any query, so accept them all.
+    let mut uri = String::new();
+    #[allow(clippy::result_large_err)]
+    let record_uri = |req: &Request, resp: Response| {
+        uri = req.uri().to_string();
+        Ok(resp)
+    };
+    let Ok(ws) = tokio_tungstenite::accept_hdr_async(stream, record_uri).await else {
+        return;
+    };
+    let (mut write_to_ws, mut read_from_ws) = ws.split();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let conn_id = {
+        let mut state = state.lock().unwrap();
+        state.connections += 1;
+        let conn_id = state.connections;
+        state.open.insert(conn_id, tx.clone());
+        state.uris.push(uri);
+        conn_id
+    };
+    // Like the real engine, tell the client which API call it's using.
+    let session = ModelingSessionData {
+        api_call_id: format!("mock-api-call-{conn_id}"),
+    };
+    let _ = tx.send(Outgoing::Msg(to_ws(WebSocketResponse::Success(
+        SuccessWebSocketResponse {
+            success: true,
+            request_id: None,
+            resp: OkWebSocketResponseData::ModelingSessionData { session },
+        },
+    ))));
+    let reader = async {
+        // The token this connection last authenticated with, if any.
+        let mut token = None;
+        while let Some(Ok(msg)) = read_from_ws.next().await {
+            let WsMsg::Text(text) = msg else {
+                continue;
+            };
+            let Ok(req) = serde_json::from_str::<WebSocketRequest>(&text) else {
+                continue;
+            };
+            state.lock().unwrap().received.push(req.clone());
+            if let WebSocketRequest::Headers { headers } = &req {
+                token = headers
+                    .get("Authorization")
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .map(ToOwned::to_owned);
+            }
+            reply(req, token.as_deref(), &state, &tx);
+        }
+    };
+    let writer = async {
+        while let Some(Outgoing::Msg(msg)) = rx.recv().await {
+            if write_to_ws.send(msg).await.is_err() {
+                break;
+            }
+        }
+        let _ = write_to_ws.close().await;
+    };
+    // Stop as soon as either the client closes the connection, or the engine decides to.
+    tokio::select! {
+        _ = reader => {}
+        _ = writer => {}
+    }
+    state.lock().unwrap().open.remove(&conn_id);
+}
+
+/// Decide how to reply to this request, and queue the reply for the connection's writer.
+/// `token` is whatever the connection authenticated with.
+fn reply(req: WebSocketRequest, token: Option<&str>, state: &Mutex<State>, tx: &mpsc::UnboundedSender<Outgoing>) {
+    match req {
+        WebSocketRequest::ModelingCmdReq(cmd) => {
+            let (delay, reply) = state.lock().unwrap().reply_to(&cmd, token).without_delays();
+            let cmd_id = cmd.cmd_id;
+            let out = match reply {
+                Reply::Success(modeling_response) => Outgoing::Msg(to_ws(success(
+                    cmd_id,
+                    OkWebSocketResponseData::Modeling { modeling_response },
+                ))),
+                Reply::Failure(errors) => Outgoing::Msg(to_ws(failure(cmd_id, errors))),
+                Reply::Export(files) => {
+                    Outgoing::Msg(to_ws_binary(success(cmd_id, OkWebSocketResponseData::Export { files })))
+                }
+                Reply::Ignore => return,
+                Reply::Disconnect => Outgoing::Close,
+                Reply::Delayed(..) => unreachable!("delays were removed above"),
+            };
+            send_later(tx.clone(), delay, out);
+        }
+        WebSocketRequest::ModelingCmdBatchReq(batch) => reply_to_batch(batch, token, state, tx),
+        WebSocketRequest::Ping {} if !state.lock().unwrap().ignore_pings => {
+            let _ = tx.send(Outgoing::Msg(to_ws(WebSocketResponse::Success(
+                SuccessWebSocketResponse {
+                    success: true,
+                    request_id: None,
+                    resp: OkWebSocketResponseData::Pong {},
+                },
+            ))));
+        }
+        // The mock engine doesn't do WebRTC, metrics or debugging, so it ignores those requests.
+        // Headers were already handled by the connection.
+        _ => {}
+    }
+}
+
+/// Reply to each command in the batch, stopping at the first failure, like the real engine.
+/// Delays, disconnects and ignored commands apply to the whole batch.
+fn reply_to_batch(
+    batch: ModelingBatch,
+    token: Option<&str>,
+    state: &Mutex<State>,
+    tx: &mpsc::UnboundedSender<Outgoing>,
+) {
+    let ModelingBatch {
+        requests,
+        batch_id,
+        responses,
+    } = batch;
+    let mut delay = Duration::ZERO;
+    let mut results = HashMap::with_capacity(requests.len());
+    let mut state = state.lock().unwrap();
+    for cmd in &requests {
+        let (cmd_delay, reply) = state.reply_to(cmd, token).without_delays();
+        delay += cmd_delay;
+        let response = match reply {
+            Reply::Success(response) => response,
+            Reply::Export(files) => OkModelingCmdResponse::Export(output::Export {
+                files: files.into_iter().map(Into::into).collect(),
+            }),
+            Reply::Failure(errors) => {
+                results.insert(cmd.cmd_id, BatchResponse::Failure { errors });
+                break;
+            }
+            Reply::Ignore => return,
+            Reply::Disconnect => {
+                send_later(tx.clone(), delay, Outgoing::Close);
+                return;
+            }
+            Reply::Delayed(..) => unreachable!("delays were removed above"),
+        };
+        results.insert(cmd.cmd_id, BatchResponse::Success { response });
+    }
+    // The engine only sends the batch's responses if the client asked for them.
+    if !responses {
+        return;
+    }
+    let resp = success(batch_id, OkWebSocketResponseData::ModelingBatch { responses: results });
+    send_later(tx.clone(), delay, Outgoing::Msg(to_ws(resp)));
+}
+
+/// Queue this message for the connection's writer, once the delay has passed.
+fn send_later(tx: mpsc::UnboundedSender<Outgoing>, delay: Duration, out: Outgoing) {
+    tokio::task::spawn(async move {
+        tokio::time::sleep(delay).await;
+        // If the send fails, the connection has already closed, so nobody wants this reply.
+        let _ = tx.send(out);
+    });
+}
+
+fn success(request_id: ModelingCmdId, resp: OkWebSocketResponseData) -> WebSocketResponse {
+    WebSocketResponse::Success(SuccessWebSocketResponse {
+        success: true,
+        request_id: Some(request_id.into()),
+        resp,
+    })
+}
+
+fn failure(request_id: ModelingCmdId, errors: Vec<ApiError>) -> WebSocketResponse {
+    WebSocketResponse::Failure(FailureWebSocketResponse {
+        success: false,
+        request_id: Some(request_id.into()),
+        errors,
+    })
+}
+
+fn to_ws(resp: WebSocketResponse) -> WsMsg {
+    WsMsg::Text(serde_json::to_string(&resp).expect("WebSocketResponse can always be serialized"))
+}
+
+fn to_ws_binary(resp: WebSocketResponse) -> WsMsg {
+    WsMsg::Binary(bson::to_vec(&resp).expect("WebSocketResponse can always be serialized"))
+}
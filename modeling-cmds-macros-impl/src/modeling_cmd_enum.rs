@@ -1,52 +1,83 @@
 use proc_macro2::TokenStream;
-use quote::quote_spanned;
+use quote::{quote, quote_spanned};
 use syn::{spanned::Spanned, ItemMod};
 
 pub fn generate(input: ItemMod) -> TokenStream {
     let span = input.span();
 
     // Parse all items from the module, to discover which enum variants should exist.
-    // Also, find the doc for each enum variant.
+    // Also, find the doc, classification and ID fields for each enum variant.
     let items = input.content.as_ref().unwrap().1.iter();
-    let (variants, docs): (Vec<_>, Vec<_>) = items
-        .filter_map(|item| {
-            // All modeling commands are public structs.
-            let syn::Item::Struct(item) = item else {
-                return None;
-            };
-            let syn::Visibility::Public(_) = item.vis else {
-                return None;
-            };
+    let mut variants = Vec::new();
+    let mut docs = Vec::new();
+    let mut classes = Vec::new();
+    let mut id_fields = Vec::new();
+    for item in items {
+        // All modeling commands are public structs.
+        let syn::Item::Struct(item) = item else {
+            continue;
+        };
+        let syn::Visibility::Public(_) = item.vis else {
+            continue;
+        };
 
-            // Copy the struct's docstring. That'll become the docstring for the enum variant.
-            let doc = item
-                .attrs
-                .iter()
-                .filter_map(|attr| match &attr.meta {
-                    syn::Meta::NameValue(syn::MetaNameValue { path, value, .. }) => {
-                        // The attribute should look like #[doc = "..."].
-                        // The attribute's key must be "doc".
-                        if !path.is_ident("doc") {
-                            return None;
-                        }
-                        // Extract the attribute's value (the docstring's contents).
-                        let syn::Expr::Lit(syn::ExprLit {
-                            lit: syn::Lit::Str(value),
-                            ..
-                        }) = value
-                        else {
-                            return None;
-                        };
-                        let doc = value.value().trim().to_owned();
-                        Some(doc)
+        // Copy the struct's docstring. That'll become the docstring for the enum variant.
+        let doc = item
+            .attrs
+            .iter()
+            .filter_map(|attr| match &attr.meta {
+                syn::Meta::NameValue(syn::MetaNameValue { path, value, .. }) => {
+                    // The attribute should look like #[doc = "..."].
+                    // The attribute's key must be "doc".
+                    if !path.is_ident("doc") {
+                        return None;
                     }
-                    _ => None,
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
-            Some((&item.ident, doc))
-        })
-        .unzip();
+                    // Extract the attribute's value (the docstring's contents).
+                    let syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(value),
+                        ..
+                    }) = value
+                    else {
+                        return None;
+                    };
+                    let doc = value.value().trim().to_owned();
+                    Some(doc)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let class = match Class::from_attrs(&item.attrs) {
+            Ok(class) => class,
+            Err(e) => return e.to_compile_error(),
+        };
+        let ids = match IdFields::from_fields(&item.fields) {
+            Ok(ids) => ids,
+            Err(e) => return e.to_compile_error(),
+        };
+        variants.push(&item.ident);
+        docs.push(doc);
+        classes.push(class);
+        id_fields.push(ids);
+    }
+
+    // Each classification method is a match on every variant.
+    let method = |get: fn(&Class) -> bool| {
+        let values = classes.iter().map(get);
+        quote! {
+            match self {#(
+                Self::#variants(_) => #values,
+            )*}
+        }
+    };
+    let is_query = method(|class| class.query);
+    let mutates_scene = method(|class| !(class.query || class.camera_only || class.display_only));
+    let affects_camera_only = method(|class| class.camera_only);
+    let requires_sketch_mode = method(|class| class.requires_sketch_mode);
+    let returns_binary = method(|class| class.returns_binary);
+    let is_safe_to_batch = method(|class| class.safe_to_batch);
+    let referenced_ids = id_method(&variants, id_fields.iter().map(|ids| &ids.referenced));
+    let created_ids = id_method(&variants, id_fields.iter().map(|ids| &ids.created));
 
     // Output the generated enum.
     quote_spanned! {span=>
@@ -64,6 +95,56 @@ pub fn generate(input: ItemMod) -> TokenStream {
             #[doc = #docs]
             #variants(kittycad_modeling_cmds::each_cmd::#variants),
         )*}
+        /// Each command's classification comes from the `#[modeling_cmd(...)]` attribute on its struct.
+        impl ModelingCmd {
+            /// Does this command only read from the engine, without changing the scene or any
+            /// other engine state? Running a query again gives the same result, so it's always
+            /// safe to retry or cache.
+            pub fn is_query(&self) -> bool {
+                #is_query
+            }
+            /// Might this command change the scene, e.g. by creating, changing or deleting entities?
+            /// Commands which only read from the engine, or only change the camera or how the scene
+            /// is displayed, don't.
+            pub fn mutates_scene(&self) -> bool {
+                #mutates_scene
+            }
+            /// Does this command only move or configure the camera?
+            pub fn affects_camera_only(&self) -> bool {
+                #affects_camera_only
+            }
+            /// Does this command only work while the engine is in sketch mode?
+            pub fn requires_sketch_mode(&self) -> bool {
+                #requires_sketch_mode
+            }
+            /// Does the engine send this command's response as binary BSON, instead of JSON text?
+            pub fn returns_binary(&self) -> bool {
+                #returns_binary
+            }
+            /// Is this command safe to run in an engine batch?
+            pub fn is_safe_to_batch(&self) -> bool {
+                #is_safe_to_batch
+            }
+            /// IDs of the entities this command refers to, e.g. the path an `ExtendPath` extends.
+            /// These were created by earlier commands, or by the engine as a side effect of them
+            /// (like the faces of an extrusion).
+            /// Doesn't include IDs which this command assigns to new entities, see [`ModelingCmd::created_ids`].
+            pub fn referenced_ids(&self) -> Vec<::uuid::Uuid> {
+                #referenced_ids
+            }
+            /// IDs which this command assigns to the entities it creates, besides the command's
+            /// own ID, e.g. the `extra_face_ids` of a `Solid3dFilletEdge`.
+            pub fn created_ids(&self) -> Vec<::uuid::Uuid> {
+                #created_ids
+            }
+        }
+        impl kittycad_modeling_cmds::validate::Validate for ModelingCmd {
+            fn validate(&self) -> Result<(), Vec<kittycad_modeling_cmds::validate::ValidationError>> {
+                match self {#(
+                    Self::#variants(cmd) => kittycad_modeling_cmds::validate::Validate::validate(cmd),
+                )*}
+            }
+        }
         /// Each modeling command (no parameters or fields).
         #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, ::parse_display::Display)]
         #[serde(rename_all = "snake_case")]
@@ -76,6 +157,12 @@ pub fn generate(input: ItemMod) -> TokenStream {
         /// into a modeling command without fields.
         impl From<ModelingCmd> for ModelingCmdEndpoint {
             fn from(v: ModelingCmd) -> Self {
+                Self::from(&v)
+            }
+        }
+        /// Converting a borrowed command works too, so the command isn't consumed.
+        impl From<&ModelingCmd> for ModelingCmdEndpoint {
+            fn from(v: &ModelingCmd) -> Self {
                 match v {#(
                     ModelingCmd::#variants(_) => Self::#variants,
                 )*}
@@ -83,3 +170,175 @@ pub fn generate(input: ItemMod) -> TokenStream {
         }
     }
 }
+
+/// Generate a method which lists some of each command's ID fields.
+fn id_method<'a>(variants: &[&syn::Ident], fields: impl Iterator<Item = &'a Vec<IdField>>) -> TokenStream {
+    let arms = variants.iter().zip(fields).map(|(variant, fields)| {
+        if fields.is_empty() {
+            return quote! { Self::#variant(_) => Vec::new(), };
+        }
+        let extend = fields.iter().map(|IdField { ident, many }| {
+            if *many {
+                quote! { ids.extend(cmd.#ident.iter().map(|&id| ::uuid::Uuid::from(id))); }
+            } else {
+                quote! { ids.push(::uuid::Uuid::from(cmd.#ident)); }
+            }
+        });
+        quote! {
+            Self::#variant(cmd) => {
+                let mut ids = Vec::new();
+                #(#extend)*
+                ids
+            }
+        }
+    });
+    quote! {
+        match self {#(#arms)*}
+    }
+}
+
+/// A field of a modeling command which holds IDs.
+struct IdField {
+    ident: syn::Ident,
+    /// Does the field hold any number of IDs (e.g. `Vec<Uuid>` or `Option<Uuid>`), instead of just one?
+    many: bool,
+}
+
+/// A modeling command's ID fields.
+/// Every `Uuid` or `ModelingCmdId` field refers to an existing entity, unless it's marked with
+/// `#[modeling_cmd(created)]`, which means the command assigns that ID to an entity it creates.
+#[derive(Default)]
+struct IdFields {
+    referenced: Vec<IdField>,
+    created: Vec<IdField>,
+}
+
+impl IdFields {
+    fn from_fields(fields: &syn::Fields) -> syn::Result<Self> {
+        let mut ids = Self::default();
+        for field in fields {
+            let created = is_created(&field.attrs)?;
+            let Some(ident) = field.ident.clone() else {
+                continue;
+            };
+            let Some(many) = id_type(&field.ty) else {
+                if created {
+                    return Err(syn::Error::new_spanned(field, "only ID fields can be created"));
+                }
+                continue;
+            };
+            let field = IdField { ident, many };
+            if created {
+                ids.created.push(field);
+            } else {
+                ids.referenced.push(field);
+            }
+        }
+        Ok(ids)
+    }
+}
+
+/// Is this field marked with `#[modeling_cmd(created)]`?
+fn is_created(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut created = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("modeling_cmd")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("created") {
+                created = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected created"))
+            }
+        })?;
+    }
+    Ok(created)
+}
+
+/// If this type holds IDs, does it hold just one (`Some(false)`), or any number (`Some(true)`),
+/// like `Vec<Uuid>`, `HashSet<Uuid>`, `Option<Uuid>` or `[Uuid; 2]`?
+fn id_type(ty: &syn::Type) -> Option<bool> {
+    let is_id = |segment: &syn::PathSegment| segment.ident == "Uuid" || segment.ident == "ModelingCmdId";
+    let path = match ty {
+        syn::Type::Path(path) => path,
+        syn::Type::Array(array) => return id_type(&array.elem).filter(|many| !many).map(|_| true),
+        _ => return None,
+    };
+    let segment = path.path.segments.last()?;
+    if is_id(segment) {
+        return Some(false);
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let mut args = args.args.iter();
+    let (Some(syn::GenericArgument::Type(syn::Type::Path(inner))), None) = (args.next(), args.next()) else {
+        return None;
+    };
+    inner
+        .path
+        .segments
+        .last()
+        .filter(|segment| is_id(segment))
+        .map(|_| true)
+}
+
+/// How a modeling command is classified, by its `#[modeling_cmd(...)]` attribute.
+/// Commands without the attribute are assumed to change the scene, because that's the safe assumption.
+#[derive(Default)]
+struct Class {
+    /// Only reads from the engine.
+    query: bool,
+    /// Only moves or configures the camera.
+    camera_only: bool,
+    /// Only changes how the scene is displayed or interacted with (e.g. selection, highlighting,
+    /// the grid), not what's in it.
+    display_only: bool,
+    /// Only works in sketch mode.
+    requires_sketch_mode: bool,
+    /// Response is sent as binary BSON.
+    returns_binary: bool,
+    /// Safe to run in an engine batch.
+    safe_to_batch: bool,
+}
+
+impl Class {
+    fn from_attrs(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut class = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("modeling_cmd")) {
+            attr.parse_nested_meta(|meta| {
+                let flag = if meta.path.is_ident("query") {
+                    &mut class.query
+                } else if meta.path.is_ident("camera_only") {
+                    &mut class.camera_only
+                } else if meta.path.is_ident("display_only") {
+                    &mut class.display_only
+                } else if meta.path.is_ident("requires_sketch_mode") {
+                    &mut class.requires_sketch_mode
+                } else if meta.path.is_ident("returns_binary") {
+                    &mut class.returns_binary
+                } else if meta.path.is_ident("safe_to_batch") {
+                    &mut class.safe_to_batch
+                } else {
+                    return Err(meta.error(
+                        "expected query, camera_only, display_only, requires_sketch_mode, returns_binary or safe_to_batch",
+                    ));
+                };
+                *flag = true;
+                Ok(())
+            })?;
+            // These describe what the command changes, so only one can apply.
+            if [class.query, class.camera_only, class.display_only]
+                .into_iter()
+                .filter(|flag| *flag)
+                .count()
+                > 1
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "a command can only be one of query, camera_only or display_only",
+                ));
+            }
+        }
+        Ok(class)
+    }
+}
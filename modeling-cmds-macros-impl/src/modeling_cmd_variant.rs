@@ -9,7 +9,10 @@ pub fn derive_nonempty(input: DeriveInput) -> TokenStream {
     let name = input.ident;
     // Delegate to whichever macro can generate code for this type (struct, enum, etc)
     match input.data {
-        syn::Data::Struct(_) => derive_nonempty_on_struct(name),
+        syn::Data::Struct(data) => match validations(&input.attrs, &data.fields) {
+            Ok(checks) => derive_nonempty_on_struct(name, checks),
+            Err(e) => e.to_compile_error(),
+        },
         syn::Data::Enum(_) => quote_spanned! {span =>
             compile_error!("ModelingCmdVariant cannot be implemented on an enum type")
         },
@@ -19,7 +22,20 @@ pub fn derive_nonempty(input: DeriveInput) -> TokenStream {
     }
 }
 
-fn derive_nonempty_on_struct(name: proc_macro2::Ident) -> TokenStream {
+fn derive_nonempty_on_struct(name: proc_macro2::Ident, checks: Vec<TokenStream>) -> TokenStream {
+    let validate = if checks.is_empty() {
+        quote! { Ok(()) }
+    } else {
+        quote! {
+            let mut errors = Vec::new();
+            #(#checks)*
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(errors)
+            }
+        }
+    };
     quote! {
         impl kittycad_modeling_cmds::ModelingCmdVariant for #name {
             type Output = kittycad_modeling_cmds::output::#name;
@@ -30,5 +46,61 @@ fn derive_nonempty_on_struct(name: proc_macro2::Ident) -> TokenStream {
                 stringify!(#name)
             }
         }
+        impl kittycad_modeling_cmds::validate::Validate for #name {
+            fn validate(&self) -> Result<(), Vec<kittycad_modeling_cmds::validate::ValidationError>> {
+                #validate
+            }
+        }
+    }
+}
+
+/// Generate the checks from the struct's `#[validate(...)]` attributes.
+/// Each field can be checked with `positive`, `max = <number>`, `non_empty` or `multiple_of = <number>`.
+/// The struct itself can be checked with `custom = <path to function>`, for checks which involve
+/// several fields. The function takes the struct and a `&mut Vec<ValidationError>` to add errors to.
+fn validations(attrs: &[syn::Attribute], fields: &syn::Fields) -> syn::Result<Vec<TokenStream>> {
+    let validate = quote! { kittycad_modeling_cmds::validate };
+    let mut checks = Vec::new();
+    for field in fields {
+        let Some(ident) = &field.ident else {
+            continue;
+        };
+        let path = ident.to_string();
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("validate")) {
+            attr.parse_nested_meta(|meta| {
+                let check = if meta.path.is_ident("positive") {
+                    quote! { #validate::positive(&mut errors, #path, &self.#ident); }
+                } else if meta.path.is_ident("max") {
+                    // Both `max = 1` and `max = 0.5` are fine, because it's compared with floats anyway.
+                    let max: f64 = match meta.value()?.parse::<syn::Lit>()? {
+                        syn::Lit::Int(lit) => lit.base10_parse()?,
+                        syn::Lit::Float(lit) => lit.base10_parse()?,
+                        lit => return Err(syn::Error::new_spanned(lit, "expected a number")),
+                    };
+                    let max = proc_macro2::Literal::f64_suffixed(max);
+                    quote! { #validate::max(&mut errors, #path, &self.#ident, #max); }
+                } else if meta.path.is_ident("non_empty") {
+                    quote! { #validate::non_empty(&mut errors, #path, self.#ident.len()); }
+                } else if meta.path.is_ident("multiple_of") {
+                    let multiple: u32 = meta.value()?.parse::<syn::LitInt>()?.base10_parse()?;
+                    quote! { #validate::multiple_of(&mut errors, #path, self.#ident, #multiple); }
+                } else {
+                    return Err(meta.error("expected positive, max, non_empty or multiple_of"));
+                };
+                checks.push(check);
+                Ok(())
+            })?;
+        }
+    }
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("validate")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("custom") {
+                return Err(meta.error("expected custom"));
+            }
+            let custom: syn::Path = meta.value()?.parse()?;
+            checks.push(quote! { #custom(self, &mut errors); });
+            Ok(())
+        })?;
     }
+    Ok(checks)
 }
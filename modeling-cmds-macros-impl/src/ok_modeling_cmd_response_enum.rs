@@ -1,5 +1,5 @@
 use proc_macro2::TokenStream;
-use quote::quote_spanned;
+use quote::{quote, quote_spanned};
 use syn::{spanned::Spanned, ItemMod};
 
 pub fn generate(input: ItemMod) -> TokenStream {
@@ -8,16 +8,25 @@ pub fn generate(input: ItemMod) -> TokenStream {
     // Parse all items from the module, to discover which enum variants should exist.
     // Also, create the doc for each enum variant.
     let items = &input.content.as_ref().unwrap().1;
-    let variants = items
+    let structs = items
         .iter()
         .filter_map(|item| {
             // All modeling commands are public structs.
             match item {
-                syn::Item::Struct(item) if matches!(item.vis, syn::Visibility::Public(_)) => Some(&item.ident),
+                syn::Item::Struct(item) if matches!(item.vis, syn::Visibility::Public(_)) => Some(item),
                 _ => None,
             }
         })
         .collect::<Vec<_>>();
+    let variants = structs.iter().map(|item| &item.ident).collect::<Vec<_>>();
+    // Outputs without any fields carry no data, so the `Empty` response is a valid way to send them.
+    let from_empty = structs.iter().map(|item| {
+        if item.fields.is_empty() {
+            quote! { OkModelingCmdResponse::Empty => Ok(Self {}), }
+        } else {
+            quote! {}
+        }
+    });
 
     // Output the generated enum.
     quote_spanned! {span=>
@@ -47,6 +56,21 @@ pub fn generate(input: ItemMod) -> TokenStream {
         }
         )*
 
+        // Likewise, generate N different `TryFrom` impls, so that the enum can be converted back
+        // into whichever output it contains. If it contains a different output, you get the enum back.
+        #(
+        impl TryFrom<OkModelingCmdResponse> for output::#variants {
+            type Error = OkModelingCmdResponse;
+            fn try_from(resp: OkModelingCmdResponse) -> std::result::Result<Self, Self::Error> {
+                match resp {
+                    OkModelingCmdResponse::#variants(x) => Ok(x),
+                    #from_empty
+                    other => Err(other),
+                }
+            }
+        }
+        )*
+
         // The `Empty` enum variant is a bit different, doesn't conform to the same pattern.
         // So define it manually.
         impl From<()> for OkModelingCmdResponse {
@@ -8,7 +8,14 @@ use syn::{DeriveInput, ItemMod};
 
 /// This will derive the trait `ModelingCmdVariant` from the `kittycad-modeling-cmds` crate.
 /// Its associated type `output` will be the corresponding modeling command output type.
-#[proc_macro_derive(ModelingCmdVariant)]
+///
+/// Classify the command with a `#[modeling_cmd(...)]` attribute, which `define_modeling_cmd_enum`
+/// reads, e.g. `#[modeling_cmd(query, returns_binary)]`. Mark ID fields which the command assigns
+/// to new entities with `#[modeling_cmd(created)]`.
+///
+/// It also derives `Validate`, from `#[validate(...)]` attributes on the struct and its fields,
+/// e.g. `#[validate(positive)]`.
+#[proc_macro_derive(ModelingCmdVariant, attributes(modeling_cmd, validate))]
 pub fn derive_modeling_cmd_variant_nonempty(input: TokenStream) -> TokenStream {
     // Parse the input into a stream of Rust syntax tokens.
     let input: DeriveInput = syn::parse2(input.into()).unwrap();
@@ -33,7 +40,8 @@ pub fn derive_modeling_cmd_output(input: TokenStream) -> TokenStream {
     TokenStream::from(modeling_cmd_output::derive(input))
 }
 
-/// Generates the OkModelingCmdResponse enum from all its variants.
+/// Generates the OkModelingCmdResponse enum from all its variants,
+/// and conversions between the enum and each variant's output type.
 #[proc_macro]
 pub fn define_ok_modeling_cmd_response_enum(item: TokenStream) -> TokenStream {
     let input: ItemMod = syn::parse2(item.into()).unwrap();
@@ -55,6 +55,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EngineUtilEvaluatePath {
             /// The path in json form (the serialized result of the kcl Sketch/Path object
             pub path_json: String,
@@ -86,6 +87,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct MovePathPen {
             /// The ID of the command which created the path.
             pub path: ModelingCmdId,
@@ -101,6 +103,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct ExtendPath {
             /// The ID of the command which created the path.
             pub path: ModelingCmdId,
@@ -119,6 +122,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct Extrude {
             /// Which sketch to extrude.
             /// Must be a closed 2D solid.
@@ -232,6 +236,7 @@ define_modeling_cmd_enum! {
             /// If true, the sweep will be broken up into sub-sweeps (extrusions, revolves, sweeps) based on the trajectory path components.
             pub sectional: bool,
             /// The maximum acceptable surface gap computed between the revolution surface joints. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
             /// What is this sweep relative to?
             #[serde(default)]
@@ -246,6 +251,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct Revolve {
             /// Which sketch to revolve.
             /// Must be a closed 2D solid.
@@ -259,6 +265,7 @@ define_modeling_cmd_enum! {
             /// The signed angle of revolution (in degrees, must be <= 360 in either direction)
             pub angle: Angle,
             /// The maximum acceptable surface gap computed between the revolution surface joints. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
             /// Should the revolution also revolve in the opposite direction along the given axis?
             /// If so, this specifies its angle.
@@ -315,6 +322,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetBodyType {
             /// The Solid3D whose body type is being queried.
             pub object_id: Uuid,
@@ -336,6 +344,7 @@ define_modeling_cmd_enum! {
             /// The signed angle of revolution (in degrees, must be <= 360 in either direction)
             pub angle: Angle,
             /// The maximum acceptable surface gap computed between the revolution surface joints. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
             /// Should the revolution also revolve in the opposite direction along the given axis?
             /// If so, this specifies its angle.
@@ -384,6 +393,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct ClosePath {
             /// Which path to close.
             pub path_id: Uuid,
@@ -394,6 +404,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct CameraDragStart {
             /// The type of camera drag interaction.
             pub interaction: CameraDragInteractionType,
@@ -406,6 +417,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct CameraDragMove {
             /// The type of camera drag interaction.
             pub interaction: CameraDragInteractionType,
@@ -423,6 +435,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct CameraDragEnd {
             /// The type of camera drag interaction.
             pub interaction: CameraDragInteractionType,
@@ -435,6 +448,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct DefaultCameraGetSettings {}
 
         /// Gets the default camera's view state
@@ -442,6 +456,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct DefaultCameraGetView {}
 
         /// Sets the default camera's view state
@@ -449,6 +464,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraSetView {
             /// Camera view state
             pub view: CameraViewState,
@@ -459,6 +475,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraLookAt {
             /// Where the camera is positioned
             pub vantage: Point3d,
@@ -478,6 +495,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraPerspectiveSettings {
             /// Where the camera is positioned
             pub vantage: Point3d,
@@ -503,6 +521,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraZoom {
             /// Move the camera forward along the vector it's looking at,
             /// by this magnitudedefaultCameraZoom.
@@ -515,6 +534,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query, returns_binary)]
         pub struct Export2d {
             /// IDs of the entities to be exported.
             pub entity_ids: Vec<Uuid>,
@@ -527,6 +547,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query, returns_binary)]
         pub struct Export3d {
             /// IDs of the entities to be exported. If this is empty, then all entities are exported.
             pub entity_ids: Vec<Uuid>,
@@ -539,6 +560,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query, returns_binary)]
         pub struct Export {
             /// IDs of the entities to be exported. If this is empty, then all entities are exported.
             pub entity_ids: Vec<Uuid>,
@@ -551,6 +573,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EntityGetParentId {
             /// ID of the entity being queried.
             pub entity_id: Uuid,
@@ -561,6 +584,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EntityGetNumChildren {
             /// ID of the entity being queried.
             pub entity_id: Uuid,
@@ -571,6 +595,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EntityGetChildUuid {
             /// ID of the entity being queried.
             pub entity_id: Uuid,
@@ -596,6 +621,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EntityGetAllChildUuids {
             /// ID of the entity being queried.
             pub entity_id: Uuid,
@@ -606,6 +632,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EntityGetSketchPaths {
             /// ID of the entity being queried.
             pub entity_id: Uuid,
@@ -616,6 +643,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct EntityGetDistance {
             /// ID of the first entity being queried.
             pub entity_id1: Uuid,
@@ -797,6 +825,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SelectWithPoint {
             /// Where in the window was selected
             pub selected_at_window: Point2d,
@@ -809,6 +838,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SelectAdd {
             /// Which entities to select
             pub entities: Vec<Uuid>,
@@ -819,6 +849,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SelectRemove {
             /// Which entities to unselect
             pub entities: Vec<Uuid>,
@@ -836,6 +867,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SelectReplace {
             /// Which entities to select
             pub entities: Vec<Uuid>,
@@ -847,6 +879,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct HighlightSetEntity {
             /// Coordinates of the window being clicked
             pub selected_at_window: Point2d,
@@ -862,6 +895,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct HighlightSetEntities {
             /// Highlight these entities.
             pub entities: Vec<Uuid>,
@@ -886,6 +920,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct UpdateAnnotation {
             /// Which annotation to update
             pub annotation_id: Uuid,
@@ -899,6 +934,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct EdgeLinesVisible {
             /// Whether or not the edge lines should be hidden.
             pub hidden: bool,
@@ -909,6 +945,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only, safe_to_batch)]
         pub struct ObjectVisible {
             /// Which object to change
             pub object_id: Uuid,
@@ -921,6 +958,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only, safe_to_batch)]
         pub struct ObjectBringToFront {
             /// Which object to change
             pub object_id: Uuid,
@@ -931,6 +969,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct ObjectSetMaterialParamsPbr {
             /// Which object to change
             pub object_id: Uuid,
@@ -951,6 +990,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct GetEntityType {
             /// ID of the entity being queried.
             pub entity_id: Uuid,
@@ -961,6 +1001,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetAllEdgeFaces {
             /// Which object is being queried.
             pub object_id: Uuid,
@@ -996,6 +1037,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct Solid2dAddHole {
             /// Which object to add the hole to.
             pub object_id: Uuid,
@@ -1008,6 +1050,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetAllOppositeEdges {
             /// Which object is being queried.
             pub object_id: Uuid,
@@ -1022,6 +1065,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetOppositeEdge {
             /// Which object is being queried.
             pub object_id: Uuid,
@@ -1036,6 +1080,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetNextAdjacentEdge {
             /// Which object is being queried.
             pub object_id: Uuid,
@@ -1050,6 +1095,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetPrevAdjacentEdge {
             /// Which object is being queried.
             pub object_id: Uuid,
@@ -1064,6 +1110,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetCommonEdge {
             /// Which object is being queried.
             pub object_id: Uuid,
@@ -1076,6 +1123,8 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
+        #[validate(custom = crate::validate::fillet_extra_face_ids)]
         pub struct Solid3dFilletEdge {
             /// Which object is being filletted.
             pub object_id: Uuid,
@@ -1087,8 +1136,10 @@ define_modeling_cmd_enum! {
             #[builder(default)]
             pub edge_ids: Vec<Uuid>,
             /// The radius of the fillet. Measured in length (using the same units that the current sketch uses). Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub radius: LengthUnit,
             /// The maximum acceptable surface gap computed between the filleted surfaces. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
             /// How to apply the cut.
             #[serde(default)]
@@ -1107,6 +1158,7 @@ define_modeling_cmd_enum! {
             /// the command ID used to send this command.
             #[serde(default)]
             #[builder(default)]
+            #[modeling_cmd(created)]
             pub extra_face_ids: Vec<Uuid>,
         }
 
@@ -1115,6 +1167,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[validate(custom = crate::validate::cut_extra_face_ids)]
         pub struct Solid3dCutEdges {
             /// Which object is being cut.
             pub object_id: Uuid,
@@ -1126,6 +1179,7 @@ define_modeling_cmd_enum! {
             pub cut_type: CutTypeV2,
             /// The maximum acceptable surface gap computed between the cut surfaces. Must be
             /// positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
             /// Which cutting algorithm to use.
             #[serde(default)]
@@ -1140,6 +1194,7 @@ define_modeling_cmd_enum! {
             /// the command ID used to send this command.
             #[serde(default)]
             #[builder(default)]
+            #[modeling_cmd(created)]
             pub extra_face_ids: Vec<Uuid>,
         }
 
@@ -1148,6 +1203,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct FaceIsPlanar {
             /// Which face is being queried.
             pub object_id: Uuid,
@@ -1158,6 +1214,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct FaceGetPosition {
             /// Which face is being queried.
             pub object_id: Uuid,
@@ -1171,6 +1228,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct FaceGetCenter {
             /// Which face is being queried.
             pub object_id: Uuid,
@@ -1181,6 +1239,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct FaceGetGradient {
             /// Which face is being queried.
             pub object_id: Uuid,
@@ -1194,6 +1253,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(safe_to_batch)]
         pub struct SendObject {
             /// Which object is being changed.
             pub object_id: Uuid,
@@ -1205,6 +1265,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only, safe_to_batch)]
         pub struct EntitySetOpacity {
             /// Which entity is being changed.
             pub entity_id: Uuid,
@@ -1219,6 +1280,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct EntityFade {
             /// Which entity is being changed.
             pub entity_id: Uuid,
@@ -1256,6 +1318,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only, safe_to_batch)]
         pub struct PlaneSetColor {
             /// Which plane is being changed.
             pub plane_id: Uuid,
@@ -1268,6 +1331,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only, safe_to_batch)]
         pub struct SetTool {
             /// What tool should be active.
             pub tool: SceneToolType,
@@ -1278,6 +1342,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct MouseMove {
             /// Where the mouse is
             pub window: Point2d,
@@ -1294,6 +1359,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct MouseClick {
             /// Where the mouse is
             pub window: Point2d,
@@ -1306,6 +1372,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(requires_sketch_mode)]
         pub struct SketchModeDisable {}
 
         /// Get the plane for sketch mode.
@@ -1313,6 +1380,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query, requires_sketch_mode)]
         pub struct GetSketchModePlane {}
 
         /// Get the plane for sketch mode.
@@ -1372,6 +1440,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetBackgroundColor {
             /// The color to set the background to.
             pub color: Color,
@@ -1382,6 +1451,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetCurrentToolProperties {
             /// The color to set the tool line to.
             pub color: Option<Color>,
@@ -1392,6 +1462,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetDefaultSystemProperties {
             /// The default system color.
             #[serde(default)]
@@ -1406,6 +1477,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct CurveGetType {
             /// Which curve to query.
             pub curve_id: Uuid,
@@ -1416,6 +1488,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct CurveGetControlPoints {
             /// Which curve to query.
             pub curve_id: Uuid,
@@ -1426,6 +1499,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct ProjectEntityToPlane {
             /// Which entity to project (vertex or edge).
             pub entity_id: Uuid,
@@ -1441,6 +1515,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct ProjectPointsToPlane {
             /// The id of the plane used for the projection.
             pub plane_id: Uuid,
@@ -1470,6 +1545,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct TakeSnapshot {
             /// What image format to return.
             pub format: ImageFormat,
@@ -1493,6 +1569,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct PathGetInfo {
             /// Which path to query
             pub path_id: Uuid,
@@ -1503,6 +1580,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct PathGetCurveUuidsForVertices {
             /// Which path to query
             pub path_id: Uuid,
@@ -1516,6 +1594,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct PathGetCurveUuid {
             /// Which path to query
             pub path_id: Uuid,
@@ -1529,6 +1608,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct PathGetVertexUuids {
             /// Which path to query
             pub path_id: Uuid,
@@ -1539,6 +1619,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct PathGetSketchTargetUuid {
             /// Which path to query
             pub path_id: Uuid,
@@ -1595,6 +1676,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct PlaneIntersectAndProject {
             /// The plane you're intersecting against.
             pub plane_id: Uuid,
@@ -1607,6 +1689,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct CurveGetEndPoints {
             /// ID of the curve being queried.
             pub curve_id: Uuid,
@@ -1617,10 +1700,13 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct ReconfigureStream {
-            /// Width of the stream.
+            /// Width of the stream. Must be a multiple of 4.
+            #[validate(multiple_of = 4)]
             pub width: u32,
-            /// Height of the stream.
+            /// Height of the stream. Must be a multiple of 4.
+            #[validate(multiple_of = 4)]
             pub height: u32,
             /// Frames per second.
             pub fps: u32,
@@ -1659,6 +1745,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Mass {
             /// IDs of the entities to get the mass of. If this is empty, then the default scene is included in
             /// the mass.
@@ -1676,6 +1763,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Density {
             /// IDs of the entities to get the density of. If this is empty, then the default scene is included in
             /// the density.
@@ -1693,6 +1781,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Volume {
             /// IDs of the entities to get the volume of. If this is empty, then the default scene is included in
             /// the volume.
@@ -1706,6 +1795,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct CenterOfMass {
             /// IDs of the entities to get the center of mass of. If this is empty, then the default scene is included in
             /// the center of mass.
@@ -1719,6 +1809,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct SurfaceArea {
             /// IDs of the entities to get the surface area of. If this is empty, then the default scene is included in
             /// the surface area.
@@ -1735,6 +1826,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraFocusOn {
             /// UUID of object to focus on.
             pub uuid: Uuid,
@@ -1747,6 +1839,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetSelectionType {
             /// What type of selection should occur when you select something?
             pub selection_type: SceneSelectionType,
@@ -1760,6 +1853,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetSelectionFilter {
             /// If vector is empty, clear all filters.
             /// If vector is non-empty, only the given entity types will be selectable.
@@ -1774,6 +1868,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct SceneGetEntityIds {
             /// The entity types to be queried.
             pub filter: Vec<EntityType>,
@@ -1785,6 +1880,7 @@ define_modeling_cmd_enum! {
             /// individually. If there are fewer than `take` items of the provided filter type then the
             /// returned list's length will be the smaller value.
             #[schemars(range(min = 1, max = 1000))]
+            #[validate(positive, max = 1000)]
             pub take: u32,
         }
 
@@ -1796,6 +1892,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraSetOrthographic {}
 
         /// Use perspective projection.
@@ -1806,6 +1903,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraSetPerspective {
             /// If this is not given, use the same parameters as last time the perspective camera was used.
             pub parameters: Option<PerspectiveCameraParameters>,
@@ -1819,6 +1917,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraCenterToSelection {
             /// Dictates whether or not the camera position should be adjusted during this operation
             /// If no movement is requested, the camera will orbit around the new center from its current position
@@ -1834,6 +1933,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct DefaultCameraCenterToScene {
             /// Dictates whether or not the camera position should be adjusted during this operation
             /// If no movement is requested, the camera will orbit around the new center from its current position
@@ -1847,6 +1947,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct ZoomToFit {
             /// Which objects to fit camera to; if empty, fit to all non-default objects. Defaults to empty vector.
             #[serde(default = "default_uuid_vector")]
@@ -1869,6 +1970,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct OrientToFace {
             /// Which face to orient camera to. If the face is not planar, no action will occur.
             pub face_id: Uuid,
@@ -1890,6 +1992,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(camera_only)]
         pub struct ViewIsometric {
             /// How much to pad the view frame by, as a fraction of the object(s) bounding box size.
             /// Negative padding will crop the view of the object proportionally.
@@ -1904,6 +2007,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetExtrusionFaceInfo {
             /// The Solid3d object whose extrusion is being queried.
             pub object_id: Uuid,
@@ -1916,6 +2020,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct Solid3dGetAdjacencyInfo {
             /// The Solid3d object whose info is being queried.
             pub object_id: Uuid,
@@ -1929,6 +2034,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SelectClear {}
 
         /// Find all IDs of selected entities
@@ -1936,6 +2042,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct SelectGet {}
 
         /// Get the number of objects in the scene
@@ -1946,6 +2053,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(query)]
         pub struct GetNumObjects {}
 
         ///Set the transform of an object.
@@ -1977,8 +2085,10 @@ define_modeling_cmd_enum! {
         {
             /// Which solids to union together.
             /// Cannot be empty.
+            #[validate(non_empty)]
             pub solid_ids: Vec<Uuid>,
             /// The maximum acceptable surface gap computed between the joined solids. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
         }
 
@@ -1996,6 +2106,7 @@ define_modeling_cmd_enum! {
             /// Which solids to intersect together
             pub solid_ids: Vec<Uuid>,
             /// The maximum acceptable surface gap computed between the joined solids. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
         }
 
@@ -2016,6 +2127,7 @@ define_modeling_cmd_enum! {
             /// Will be cut out from the 'target'.
             pub tool_ids: Vec<Uuid>,
             /// The maximum acceptable surface gap computed between the target and the solids cut out from it. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
         }
 
@@ -2032,6 +2144,7 @@ define_modeling_cmd_enum! {
             /// Which input bodies to intersect.  Inputs with non-solid body types are permitted
             pub body_ids: Vec<Uuid>,
             /// The maximum acceptable surface gap between the intersected bodies. Must be positive (i.e. greater than zero).
+            #[validate(positive)]
             pub tolerance: LengthUnit,
         }
 
@@ -2071,6 +2184,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetGridReferencePlane {
             /// The grid to be moved.
             pub grid_id: Uuid,
@@ -2084,6 +2198,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetGridScale {
             /// Distance between grid lines represents this much distance.
             pub value: f32,
@@ -2097,6 +2212,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetGridAutoScale {
         }
 
@@ -2106,6 +2222,7 @@ define_modeling_cmd_enum! {
         #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
         #[cfg_attr(feature = "ts-rs", ts(export_to = "ModelingCmd.ts"))]
         #[cfg_attr(not(feature = "unstable_exhaustive"), non_exhaustive)]
+        #[modeling_cmd(display_only)]
         pub struct SetOrderIndependentTransparency {
             /// Enables or disables OIT.
             /// If not given, toggles it.
@@ -2114,30 +2231,6 @@ define_modeling_cmd_enum! {
     }
 }
 
-impl ModelingCmd {
-    /// Is this command safe to run in an engine batch?
-    pub fn is_safe_to_batch(&self) -> bool {
-        use ModelingCmd::*;
-        matches!(
-            self,
-            MovePathPen(_)
-                | ExtendPath(_)
-                | Extrude(_)
-                | Revolve(_)
-                | Solid3dFilletEdge(_)
-                | ClosePath(_)
-                | UpdateAnnotation(_)
-                | ObjectVisible(_)
-                | ObjectBringToFront(_)
-                | Solid2dAddHole(_)
-                | SendObject(_)
-                | EntitySetOpacity(_)
-                | PlaneSetColor(_)
-                | SetTool(_)
-        )
-    }
-}
-
 /// File to import into the current model.
 /// If you are sending binary data for a file, be sure to send the WebSocketRequest as
 /// binary/bson, not text/json.
@@ -2154,3 +2247,46 @@ pub struct ImportFile {
     )]
     pub data: Vec<u8>,
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn classification() {
+        let query = ModelingCmd::GetNumObjects(GetNumObjects::default());
+        assert!(query.is_query());
+        assert!(!query.mutates_scene());
+        assert!(!query.returns_binary());
+
+        let camera = ModelingCmd::DefaultCameraSetOrthographic(DefaultCameraSetOrthographic::default());
+        assert!(camera.affects_camera_only());
+        assert!(!camera.mutates_scene());
+
+        let display = ModelingCmd::SelectClear(SelectClear::default());
+        assert!(!display.mutates_scene());
+        assert!(!display.is_query());
+
+        let mutation = ModelingCmd::StartPath(StartPath::default());
+        assert!(mutation.mutates_scene());
+        assert!(!mutation.is_query());
+        assert!(!mutation.affects_camera_only());
+        assert!(!mutation.requires_sketch_mode());
+
+        let sketch = ModelingCmd::SketchModeDisable(SketchModeDisable::default());
+        assert!(sketch.requires_sketch_mode());
+    }
+
+    #[test]
+    fn ids() {
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let common_edge = ModelingCmd::Solid3dGetCommonEdge(Solid3dGetCommonEdge {
+            object_id: ids[0],
+            face_ids: [ids[1], ids[2]],
+        });
+        assert_eq!(common_edge.referenced_ids(), ids);
+        assert!(common_edge.created_ids().is_empty());
+    }
+}
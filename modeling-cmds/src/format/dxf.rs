@@ -1,3 +1,6 @@
+/// Import sketches from DXF files, on the client.
+pub mod import;
+
 /// Export sketches in DXF format.
 pub mod export {
     use parse_display::{Display, FromStr};
@@ -0,0 +1,902 @@
+use std::{f64::consts::PI, fmt};
+
+use crate::{
+    id::{IdGenerator, ModelingCmdId},
+    length_unit::LengthUnit,
+    shared::{Angle, PathSegment, Point2d},
+    units::UnitLength,
+    ClosePath, ExtendPath, ModelingCmd, MovePathPen, StartPath,
+};
+
+/// Options for reading DXF files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// The unit for the commands' lengths.
+    /// Drawings which don't say what unit they're in (with the `$INSUNITS` header variable),
+    /// or say it's a unit this doesn't know, are assumed to be in this unit too.
+    pub units: UnitLength,
+}
+
+/// Read the 2D entities in a DXF file, as the commands which draw them as paths.
+///
+/// Each LINE, ARC, CIRCLE, LWPOLYLINE, SPLINE and ELLIPSE in the file's ENTITIES section becomes
+/// its own path: a `StartPath`, a `MovePathPen` to where the entity starts, an `ExtendPath` for
+/// each of its segments, and a `ClosePath` if it's closed. Other entities, like text, dimensions
+/// and block references, are skipped. Entities are flattened onto the XY plane.
+///
+/// Command IDs come from `ids`, so reading the same file with the same generator always gives
+/// the same commands.
+/// Only ASCII DXF files can be read so far.
+pub fn read(
+    input: &[u8],
+    options: &Options,
+    ids: &mut IdGenerator,
+) -> Result<Vec<(ModelingCmdId, ModelingCmd)>, Error> {
+    if input.starts_with(BINARY_SENTINEL) {
+        return Err(Error::Binary);
+    }
+    // DXF files are often in the drawing program's code page, not UTF-8, but everything this
+    // reads is ASCII anyway.
+    let text = String::from_utf8_lossy(input);
+    let groups = groups(&text)?;
+
+    let mut unit_in_mm = None;
+    let mut section = None;
+    let mut entities = Vec::new();
+    for record in records(&groups) {
+        match record.kind {
+            "SECTION" => {
+                section = record.text(2);
+                if section == Some("HEADER") {
+                    unit_in_mm = record.header_unit_in_mm()?;
+                }
+            }
+            "ENDSEC" => section = None,
+            _ if section == Some("ENTITIES") => entities.push(record),
+            _ => {}
+        }
+    }
+
+    let mut writer = Writer {
+        ids,
+        scale: unit_in_mm.map_or(1.0, |mm| UnitLength::Millimeters.convert_to(options.units, mm)),
+        // No path has been started yet.
+        path: ModelingCmdId(uuid::Uuid::nil()),
+        cmds: Vec::new(),
+    };
+    for entity in entities {
+        match entity.kind {
+            "LINE" => writer.line(&entity)?,
+            "ARC" => writer.arc(&entity, false)?,
+            "CIRCLE" => writer.arc(&entity, true)?,
+            "LWPOLYLINE" => writer.polyline(&entity)?,
+            "SPLINE" => writer.spline(&entity)?,
+            "ELLIPSE" => writer.ellipse(&entity)?,
+            _ => {}
+        }
+    }
+    Ok(writer.cmds)
+}
+
+/// Why a DXF file couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The file is a binary DXF file, which can't be read yet.
+    Binary,
+    /// The file isn't valid DXF.
+    Syntax {
+        /// Line of the file where the problem is.
+        line: usize,
+        /// What's wrong.
+        message: String,
+    },
+    /// An entity can't be drawn with path commands.
+    UnsupportedEntity {
+        /// Line of the file where the entity starts.
+        line: usize,
+        /// What kind of entity it is, e.g. `SPLINE`.
+        kind: String,
+        /// Why it can't be drawn.
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binary => write!(
+                f,
+                "binary DXF files aren't supported yet, please save the file as ASCII DXF"
+            ),
+            Self::Syntax { line, message } => write!(f, "invalid DXF on line {line}: {message}"),
+            Self::UnsupportedEntity { line, kind, reason } => {
+                write!(f, "can't draw the {kind} on line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// How binary DXF files start.
+const BINARY_SENTINEL: &[u8] = b"AutoCAD Binary DXF\r\n\x1a\0";
+
+/// A DXF file is a list of groups, which are each a code line followed by a value line.
+/// The code says what the value means, e.g. 10 is usually an X coordinate.
+#[derive(Debug)]
+struct Group<'a> {
+    code: i32,
+    value: &'a str,
+    /// Line of the file that the group starts on, i.e. its code's line.
+    line: usize,
+}
+
+fn groups(text: &str) -> Result<Vec<Group<'_>>, Error> {
+    let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+    let mut groups = Vec::new();
+    while let Some((line, code)) = lines.next() {
+        let code = code.parse().map_err(|_| Error::Syntax {
+            line,
+            message: format!("expected a group code, found {code:?}"),
+        })?;
+        let (_, value) = lines.next().ok_or(Error::Syntax {
+            line,
+            message: format!("group {code} has no value"),
+        })?;
+        groups.push(Group { code, value, line });
+        // Anything after the end of the file, like blank lines, isn't DXF.
+        if code == 0 && value == "EOF" {
+            break;
+        }
+    }
+    Ok(groups)
+}
+
+/// An entity, or another object in the file, like the start of a section.
+struct Record<'a> {
+    kind: &'a str,
+    /// Line of the file where it starts.
+    line: usize,
+    /// Its groups, after the one which says what kind it is.
+    groups: &'a [Group<'a>],
+}
+
+/// Each record starts with a group with code 0, which says what kind of record it is.
+fn records<'a>(groups: &'a [Group<'a>]) -> Vec<Record<'a>> {
+    let starts: Vec<usize> = (0..groups.len()).filter(|&i| groups[i].code == 0).collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| Record {
+            kind: groups[start].value,
+            line: groups[start].line,
+            groups: &groups[start + 1..starts.get(i + 1).copied().unwrap_or(groups.len())],
+        })
+        .collect()
+}
+
+impl<'a> Record<'a> {
+    /// The first value with this code.
+    fn text(&self, code: i32) -> Option<&'a str> {
+        self.groups
+            .iter()
+            .find(|group| group.code == code)
+            .map(|group| group.value)
+    }
+
+    /// The first value with this code, as a number, or `default` if there isn't one.
+    fn number(&self, code: i32, default: f64) -> Result<f64, Error> {
+        match self.groups.iter().find(|group| group.code == code) {
+            Some(group) => number(group),
+            None => Ok(default),
+        }
+    }
+
+    /// Every value with this code, as numbers.
+    fn numbers(&self, code: i32) -> Result<Vec<f64>, Error> {
+        self.groups
+            .iter()
+            .filter(|group| group.code == code)
+            .map(number)
+            .collect()
+    }
+
+    /// The point whose X coordinate has this code. Its Y coordinate's code is 10 more.
+    fn point(&self, code: i32) -> Result<Point2d<f64>, Error> {
+        Ok(Point2d {
+            x: self.number(code, 0.0)?,
+            y: self.number(code + 10, 0.0)?,
+        })
+    }
+
+    /// The first value with this code, as an integer.
+    fn integer(&self, code: i32) -> Result<i64, Error> {
+        self.number(code, 0.0).map(|value| value as i64)
+    }
+
+    /// Is the entity upside down, i.e. does its extrusion direction point down the z-axis?
+    /// Then its coordinates are mirrored, and it turns the other way.
+    /// Other extrusion directions are just flattened.
+    fn is_upside_down(&self) -> Result<bool, Error> {
+        Ok(self.number(230, 1.0)? < 0.0)
+    }
+
+    /// How long the unit set by the `$INSUNITS` header variable is, in millimeters, if this is
+    /// the header section and it sets a unit.
+    fn header_unit_in_mm(&self) -> Result<Option<f64>, Error> {
+        let Some(index) = self
+            .groups
+            .iter()
+            .position(|group| group.code == 9 && group.value == "$INSUNITS")
+        else {
+            return Ok(None);
+        };
+        let Some(group) = self.groups.get(index + 1).filter(|group| group.code == 70) else {
+            return Ok(None);
+        };
+        let mm = match number(group)? as i64 {
+            1 => 25.4,
+            2 => 304.8,
+            3 => 1_609_344.0,
+            4 => 1.0,
+            5 => 10.0,
+            6 => 1e3,
+            7 => 1e6,
+            // Microinches and mils.
+            8 => 25.4e-6,
+            9 => 25.4e-3,
+            10 => 914.4,
+            // Ångströms, nanometers and microns.
+            11 => 1e-7,
+            12 => 1e-6,
+            13 => 1e-3,
+            // Decimeters, decameters, hectometers and gigameters.
+            14 => 1e2,
+            15 => 1e4,
+            16 => 1e5,
+            17 => 1e12,
+            // Astronomical units, light years and parsecs.
+            18 => 1.495_978_707e14,
+            19 => 9.460_730_472_580_8e18,
+            20 => 3.085_677_581_491_367e19,
+            // US survey feet.
+            21 => 1_200_000.0 / 3937.0,
+            // 0 means the drawing has no unit, and other codes aren't units at all.
+            _ => return Ok(None),
+        };
+        Ok(Some(mm))
+    }
+
+    fn unsupported(&self, reason: &'static str) -> Error {
+        Error::UnsupportedEntity {
+            line: self.line,
+            kind: self.kind.to_owned(),
+            reason,
+        }
+    }
+}
+
+fn number(group: &Group) -> Result<f64, Error> {
+    group.value.parse().map_err(|_| Error::Syntax {
+        line: group.line + 1,
+        message: format!("group {} should be a number, but it was {:?}", group.code, group.value),
+    })
+}
+
+/// Writes the commands which draw each entity.
+struct Writer<'a> {
+    ids: &'a mut IdGenerator,
+    /// What to multiply the file's lengths by, to convert them to the commands' unit.
+    scale: f64,
+    /// The path being drawn.
+    path: ModelingCmdId,
+    cmds: Vec<(ModelingCmdId, ModelingCmd)>,
+}
+
+impl Writer<'_> {
+    fn push(&mut self, cmd: ModelingCmd) {
+        let id = self.ids.next_id();
+        self.cmds.push((id, cmd));
+    }
+
+    /// Start a new path at this point.
+    fn start(&mut self, at: Point2d<f64>) {
+        self.path = self.ids.next_id();
+        self.cmds
+            .push((self.path, ModelingCmd::StartPath(StartPath::default())));
+        let to = self.point(at).with_z(LengthUnit(0.0));
+        self.push(ModelingCmd::MovePathPen(
+            MovePathPen::builder().path(self.path).to(to).build(),
+        ));
+    }
+
+    fn extend(&mut self, segment: PathSegment) {
+        self.push(ModelingCmd::ExtendPath(
+            ExtendPath::builder().path(self.path).segment(segment).build(),
+        ));
+    }
+
+    fn close(&mut self) {
+        self.push(ModelingCmd::ClosePath(
+            ClosePath::builder().path_id(self.path.into()).build(),
+        ));
+    }
+
+    fn point(&self, p: Point2d<f64>) -> Point2d<LengthUnit> {
+        (p * self.scale).map(LengthUnit)
+    }
+
+    fn line_to(&mut self, end: Point2d<f64>) {
+        self.extend(PathSegment::Line {
+            end: self.point(end).with_z(LengthUnit(0.0)),
+            relative: false,
+        });
+    }
+
+    fn line(&mut self, entity: &Record) -> Result<(), Error> {
+        self.start(entity.point(10)?);
+        self.line_to(entity.point(11)?);
+        Ok(())
+    }
+
+    /// Draw an ARC, or a CIRCLE, which is an arc all the way round.
+    fn arc(&mut self, entity: &Record, circle: bool) -> Result<(), Error> {
+        let mut center = entity.point(10)?;
+        let radius = entity.number(40, 0.0)?;
+        let (mut start, mut end) = if circle {
+            (0.0, 360.0)
+        } else {
+            (entity.number(50, 0.0)?, entity.number(51, 0.0)?)
+        };
+        // Arcs go anticlockwise from start to end.
+        if end <= start {
+            end += 360.0;
+        }
+        if entity.is_upside_down()? {
+            center.x = -center.x;
+            (start, end) = (180.0 - start, 180.0 - end);
+        }
+        let first = start.to_radians();
+        self.start(
+            center
+                + Point2d {
+                    x: first.cos(),
+                    y: first.sin(),
+                } * radius,
+        );
+        self.extend(PathSegment::Arc {
+            center: self.point(center),
+            radius: LengthUnit(radius * self.scale),
+            start: Angle::from_degrees(start),
+            end: Angle::from_degrees(end),
+            relative: false,
+        });
+        if circle {
+            self.close();
+        }
+        Ok(())
+    }
+
+    fn polyline(&mut self, entity: &Record) -> Result<(), Error> {
+        // Each vertex's bulge describes the segment from it to the next vertex.
+        let mut vertices: Vec<(Point2d<f64>, f64)> = Vec::new();
+        for group in entity.groups {
+            match group.code {
+                10 => vertices.push((
+                    Point2d {
+                        x: number(group)?,
+                        y: 0.0,
+                    },
+                    0.0,
+                )),
+                20 => {
+                    if let Some((vertex, _)) = vertices.last_mut() {
+                        vertex.y = number(group)?;
+                    }
+                }
+                42 => {
+                    if let Some((_, bulge)) = vertices.last_mut() {
+                        *bulge = number(group)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if entity.is_upside_down()? {
+            for (vertex, bulge) in &mut vertices {
+                vertex.x = -vertex.x;
+                *bulge = -*bulge;
+            }
+        }
+        let Some(&(first, _)) = vertices.first() else {
+            return Ok(());
+        };
+        let closed = entity.integer(70)? & 1 != 0;
+        self.start(first);
+        let next = vertices.iter().skip(1).chain(closed.then_some(&vertices[0]));
+        for (&(start, bulge), &(end, _)) in vertices.iter().zip(next) {
+            self.bulge_to(start, end, bulge);
+        }
+        if closed {
+            self.close();
+        }
+        Ok(())
+    }
+
+    /// Draw a polyline segment: a line if its bulge is 0, otherwise an arc.
+    /// The bulge is the tangent of a quarter of the arc's angle, and it's positive if the arc
+    /// goes anticlockwise.
+    fn bulge_to(&mut self, start: Point2d<f64>, end: Point2d<f64>, bulge: f64) {
+        // Polylines often repeat a vertex, which would be an arc with no radius.
+        if start == end {
+            return;
+        }
+        if bulge == 0.0 {
+            self.line_to(end);
+            return;
+        }
+        // The middle of the arc is the chord's midpoint, moved sideways by the arc's height,
+        // which is bulge * half the chord.
+        let chord = end - start;
+        let sideways = Point2d {
+            x: chord.y,
+            y: -chord.x,
+        };
+        let interior = (start + end) / 2.0 + sideways * (bulge / 2.0);
+        self.extend(PathSegment::ArcTo {
+            interior: self.point(interior).with_z(LengthUnit(0.0)),
+            end: self.point(end).with_z(LengthUnit(0.0)),
+            relative: false,
+        });
+    }
+
+    fn ellipse(&mut self, entity: &Record) -> Result<(), Error> {
+        let center = entity.point(10)?;
+        // The major axis is relative to the center.
+        let major_axis = entity.point(11)?;
+        let ratio = entity.number(40, 1.0)?;
+        let mut start = entity.number(41, 0.0)?;
+        let mut end = entity.number(42, 2.0 * PI)?;
+        if end <= start {
+            end += 2.0 * PI;
+        }
+        let whole = end - start >= 2.0 * PI - 1e-9;
+        // Ellipses are in world coordinates, but upside down ones still turn the other way,
+        // because their minor axis is on the other side.
+        if entity.is_upside_down()? {
+            (start, end) = (-start, -end);
+        }
+        let minor_axis = Point2d {
+            x: -major_axis.y,
+            y: major_axis.x,
+        } * ratio;
+        self.start(center + major_axis * start.cos() + minor_axis * start.sin());
+        let major_radius = major_axis.x.hypot(major_axis.y);
+        self.extend(PathSegment::Ellipse {
+            center: self.point(center),
+            major_axis: self.point(major_axis),
+            minor_radius: LengthUnit(major_radius * ratio * self.scale),
+            start_angle: Angle::from_radians(start),
+            end_angle: Angle::from_radians(end),
+        });
+        if whole {
+            self.close();
+        }
+        Ok(())
+    }
+
+    fn spline(&mut self, entity: &Record) -> Result<(), Error> {
+        let degree = entity.integer(71)?;
+        let knots = entity.numbers(40)?;
+        let xs = entity.numbers(10)?;
+        let ys = entity.numbers(20)?;
+        let mut weights = entity.numbers(41)?;
+        if xs.is_empty() {
+            return Err(entity.unsupported("splines defined only by fit points aren't supported"));
+        }
+        if !(1..=3).contains(&degree) {
+            return Err(entity.unsupported("only splines of degree 1, 2 or 3 can be drawn"));
+        }
+        let degree = degree as usize;
+        if weights.len() != xs.len() {
+            weights = vec![1.0; xs.len()];
+        }
+        // Each Bézier curve needs `degree + 1` control points.
+        if xs.len() <= degree || ys.len() != xs.len() || knots.len() != xs.len() + degree + 1 {
+            return Err(entity.unsupported("the spline's knots and control points don't match"));
+        }
+        // A knot repeated more than `degree` times breaks the spline in two.
+        let broken = knots[1..knots.len() - 1]
+            .windows(degree + 1)
+            .any(|knots| knots.iter().all(|&knot| knot == knots[0]));
+        if broken {
+            return Err(entity.unsupported("splines with breaks in them can't be drawn"));
+        }
+        // Bézier curves start at their first control point and end at their last, and so do
+        // clamped splines, whose first and last knots are each repeated degree + 1 times.
+        let clamped = |knots: &[f64]| knots.iter().all(|&knot| knot == knots[0]);
+        if !clamped(&knots[..=degree]) || !clamped(&knots[knots.len() - degree - 1..]) {
+            return Err(entity.unsupported("only clamped splines can be drawn"));
+        }
+        let weighted: Vec<[f64; 3]> = xs
+            .iter()
+            .zip(&ys)
+            .zip(&weights)
+            .map(|((&x, &y), &w)| [x * w, y * w, w])
+            .collect();
+        let beziers = bezier_segments(degree, &knots, &weighted);
+        let rational = weights
+            .iter()
+            .any(|&w| (w - weights[0]).abs() > 1e-12 * weights[0].abs());
+        if rational && degree == 3 {
+            return Err(entity.unsupported("rational cubic splines can't be drawn exactly"));
+        }
+
+        let unweight = |[x, y, w]: [f64; 3]| Point2d { x: x / w, y: y / w };
+        let first = unweight(weighted[0]);
+        self.start(first);
+        let mut end = first;
+        for bezier in beziers {
+            let points: Vec<Point2d<f64>> = bezier.iter().copied().map(unweight).collect();
+            end = points[degree];
+            let to = |writer: &Self, p: Point2d<f64>| writer.point(p).with_z(LengthUnit(0.0));
+            let segment = match degree {
+                1 => PathSegment::Line {
+                    end: to(self, end),
+                    relative: false,
+                },
+                2 if rational => {
+                    // A rational quadratic Bézier curve is a conic, whose tangents at its ends
+                    // point to its middle control point.
+                    let [q0, q1, q2] = [bezier[0], bezier[1], bezier[2]];
+                    let middle = unweight([0, 1, 2].map(|i| (q0[i] + 2.0 * q1[i] + q2[i]) / 4.0));
+                    PathSegment::ConicTo {
+                        interior: self.point(middle),
+                        end: self.point(end),
+                        start_tangent: self.point(points[1] - points[0]),
+                        end_tangent: self.point(points[2] - points[1]),
+                        relative: false,
+                    }
+                }
+                // A quadratic Bézier curve is a cubic one whose control points are 2/3 of the
+                // way to its middle control point.
+                2 => PathSegment::Bezier {
+                    control1: to(self, points[0] + (points[1] - points[0]) * (2.0 / 3.0)),
+                    control2: to(self, points[2] + (points[1] - points[2]) * (2.0 / 3.0)),
+                    end: to(self, end),
+                    relative: false,
+                },
+                _ => PathSegment::Bezier {
+                    control1: to(self, points[1]),
+                    control2: to(self, points[2]),
+                    end: to(self, end),
+                    relative: false,
+                },
+            };
+            self.extend(segment);
+        }
+        if entity.integer(70)? & 1 != 0 && end == first {
+            self.close();
+        }
+        Ok(())
+    }
+}
+
+/// Split a clamped B-spline into Bézier curves, by inserting knots until each of its internal
+/// knots is repeated `degree` times (algorithm A5.6 from The NURBS Book).
+/// None of its internal knots can already be repeated more than that.
+/// Control points are weighted, i.e. `[w x, w y, w]`, so rational splines work too.
+fn bezier_segments(degree: usize, knots: &[f64], points: &[[f64; 3]]) -> Vec<Vec<[f64; 3]>> {
+    let p = degree;
+    let m = knots.len() - 1;
+    let mut segments = vec![points[..=p].to_vec()];
+    let (mut a, mut b) = (p, p + 1);
+    while b < m {
+        let i = b;
+        while b < m && knots[b + 1] == knots[b] {
+            b += 1;
+        }
+        let multiplicity = b - i + 1;
+        let current = segments.len() - 1;
+        if b < m {
+            segments.push(vec![[0.0; 3]; p + 1]);
+        }
+        if multiplicity < p {
+            let numerator = knots[b] - knots[a];
+            let alphas: Vec<f64> = (multiplicity + 1..=p)
+                .map(|j| numerator / (knots[a + j] - knots[a]))
+                .collect();
+            let insertions = p - multiplicity;
+            for j in 1..=insertions {
+                let save = insertions - j;
+                let s = multiplicity + j;
+                for k in (s..=p).rev() {
+                    let alpha = alphas[k - s];
+                    let (before, point) = (segments[current][k - 1], segments[current][k]);
+                    segments[current][k] = [0, 1, 2].map(|c| alpha * point[c] + (1.0 - alpha) * before[c]);
+                }
+                if b < m {
+                    segments[current + 1][save] = segments[current][p];
+                }
+            }
+        }
+        if b < m {
+            for i in p - multiplicity..=p {
+                segments[current + 1][i] = points[b - p + i];
+            }
+            a = b;
+            b += 1;
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        path::{Curve, Paths},
+        shared::Point3d,
+    };
+
+    /// A DXF file with these groups in its ENTITIES section, in inches.
+    fn dxf(entities: &[(i32, &str)]) -> String {
+        dxf_in("1", entities)
+    }
+
+    /// A DXF file with these groups in its ENTITIES section, in the unit with this `$INSUNITS` code.
+    fn dxf_in(units: &str, entities: &[(i32, &str)]) -> String {
+        let header = [
+            (0, "SECTION"),
+            (2, "HEADER"),
+            (9, "$INSUNITS"),
+            (70, units),
+            (0, "ENDSEC"),
+        ];
+        let entities = [(0, "SECTION"), (2, "ENTITIES")]
+            .iter()
+            .chain(entities)
+            .chain(&[(0, "ENDSEC"), (0, "EOF")]);
+        header
+            .iter()
+            .chain(entities)
+            .map(|(code, value)| format!("  {code}\r\n{value}\r\n"))
+            .collect()
+    }
+
+    fn read_paths(input: &str) -> (Vec<(ModelingCmdId, ModelingCmd)>, Paths) {
+        let options = Options {
+            units: UnitLength::Millimeters,
+        };
+        let cmds = read(input.as_bytes(), &options, &mut IdGenerator::from_name("dxf")).unwrap();
+        let mut paths = Paths::new();
+        for (id, cmd) in &cmds {
+            paths.apply(*id, cmd).unwrap();
+        }
+        (cmds, paths)
+    }
+
+    #[test]
+    fn entities() {
+        let input = dxf(&[
+            (0, "LINE"),
+            (8, "0"),
+            (10, "0"),
+            (20, "0"),
+            (11, "1"),
+            (21, "0"),
+            (0, "CIRCLE"),
+            (10, "0"),
+            (20, "0"),
+            (40, "1"),
+            (0, "TEXT"),
+            (1, "ignored"),
+            // A 1 inch square, whose top is a semicircle bulging upwards.
+            (0, "LWPOLYLINE"),
+            (90, "4"),
+            (70, "1"),
+            (10, "0"),
+            (20, "0"),
+            (10, "1"),
+            (20, "0"),
+            (10, "1"),
+            (20, "1"),
+            (42, "1"),
+            (10, "0"),
+            (20, "1"),
+        ]);
+        let (cmds, paths) = read_paths(&input);
+        // Line: start, move, extend. Circle: start, move, extend, close.
+        // Polyline: start, move, 4 extends, close.
+        assert_eq!(cmds.len(), 3 + 4 + 7);
+        let paths: Vec<_> = paths.iter().map(|(_, path)| path).collect();
+        assert_eq!(paths.len(), 3);
+        assert!((paths[0].length() - 25.4).abs() < 1e-9);
+        assert!((paths[1].length() - 2.0 * PI * 25.4).abs() < 1e-9);
+        let bbox = paths[2].bounding_box().unwrap();
+        assert!((bbox.max.y - 1.5 * 25.4).abs() < 1e-9, "{bbox:?}");
+        assert!(matches!(paths[2].segments()[2], Curve::Arc { .. }));
+    }
+
+    fn assert_near(p: Point3d<f64>, x: f64, y: f64) {
+        assert!(
+            (p.x - x * 25.4).abs() < 1e-9 && (p.y - y * 25.4).abs() < 1e-9,
+            "{p:?} isn't ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn arcs_and_ellipses() {
+        // A quarter circle, and a quarter of an ellipse twice as wide as it is tall,
+        // each the right way up and then upside down.
+        let arc = [(0, "ARC"), (10, "1"), (20, "0"), (40, "1"), (50, "0"), (51, "90")];
+        let ellipse = [
+            (0, "ELLIPSE"),
+            (10, "0"),
+            (20, "0"),
+            (11, "2"),
+            (21, "0"),
+            (40, "0.5"),
+            (41, "0"),
+            (42, "1.5707963267948966"),
+        ];
+        let upside_down = [(230, "-1")];
+        let entities: Vec<_> = [&arc[..], &arc, &upside_down, &ellipse, &ellipse, &upside_down].concat();
+        let (_, paths) = read_paths(&dxf(&entities));
+        let curves: Vec<Curve> = paths.iter().map(|(_, path)| path.segments()[0]).collect();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+
+        // The arc goes anticlockwise, from 0° to 90°.
+        assert_near(curves[0].start(), 2.0, 0.0);
+        assert_near(curves[0].point_at(0.5), 1.0 + half, half);
+        assert_near(curves[0].end(), 1.0, 1.0);
+        // Upside down, its coordinates are mirrored, so it goes clockwise on the other side.
+        assert_near(curves[1].start(), -2.0, 0.0);
+        assert_near(curves[1].point_at(0.5), -1.0 - half, half);
+        assert_near(curves[1].end(), -1.0, 1.0);
+
+        // The ellipse goes anticlockwise, from the end of its major axis to the end of its minor one.
+        assert_near(curves[2].start(), 2.0, 0.0);
+        assert_near(curves[2].point_at(0.5), 2.0 * half, half);
+        assert_near(curves[2].end(), 0.0, 1.0);
+        // Upside down, its major axis stays put, but its minor axis points the other way.
+        assert_near(curves[3].start(), 2.0, 0.0);
+        assert_near(curves[3].point_at(0.5), 2.0 * half, -half);
+        assert_near(curves[3].end(), 0.0, -1.0);
+    }
+
+    #[test]
+    fn units() {
+        let line = [(0, "LINE"), (10, "0"), (20, "0"), (11, "1000"), (21, "0")];
+        // Microns.
+        let (_, paths) = read_paths(&dxf_in("13", &line));
+        assert!((paths.iter().next().unwrap().1.length() - 1.0).abs() < 1e-9);
+        // Units this doesn't know are treated like no unit, i.e. the options' unit.
+        let (_, paths) = read_paths(&dxf_in("99", &line));
+        assert!((paths.iter().next().unwrap().1.length() - 1000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn splines() {
+        // A cubic spline with two spans, and a quarter of a circle as a rational quadratic spline.
+        let half = std::f64::consts::FRAC_1_SQRT_2.to_string();
+        let input = dxf(&[
+            (0, "SPLINE"),
+            (71, "3"),
+            (40, "0"),
+            (40, "0"),
+            (40, "0"),
+            (40, "0"),
+            (40, "0.5"),
+            (40, "1"),
+            (40, "1"),
+            (40, "1"),
+            (40, "1"),
+            (10, "0"),
+            (20, "0"),
+            (10, "1"),
+            (20, "1"),
+            (10, "2"),
+            (20, "-1"),
+            (10, "3"),
+            (20, "1"),
+            (10, "4"),
+            (20, "0"),
+            (0, "SPLINE"),
+            (71, "2"),
+            (40, "0"),
+            (40, "0"),
+            (40, "0"),
+            (40, "1"),
+            (40, "1"),
+            (40, "1"),
+            (10, "1"),
+            (20, "0"),
+            (10, "1"),
+            (20, "1"),
+            (10, "0"),
+            (20, "1"),
+            (41, "1"),
+            (41, &half),
+            (41, "1"),
+        ]);
+        let (_, paths) = read_paths(&input);
+        let paths: Vec<_> = paths.iter().map(|(_, path)| path).collect();
+        let cubic = paths[0].segments();
+        assert_eq!(cubic.len(), 2);
+        assert!(cubic.iter().all(|curve| matches!(curve, Curve::Bezier { .. })));
+        // Splitting at t = 0.5 gives a point on the spline, where its two Bézier curves meet.
+        let middle = cubic[0].end();
+        assert_eq!(middle, cubic[1].start());
+        // The spline is symmetric, so its middle is halfway along.
+        assert!((middle.x - 2.0 * 25.4).abs() < 1e-9, "{middle:?}");
+        assert!((cubic[1].end().x - 4.0 * 25.4).abs() < 1e-9);
+
+        let arc = paths[1].segments()[0];
+        assert!(matches!(arc, Curve::Conic { .. }));
+        for t in [0.0, 0.3, 0.5, 0.8, 1.0] {
+            let p = arc.point_at(t);
+            assert!((p.x.hypot(p.y) - 25.4).abs() < 1e-9, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let options = Options::default();
+        let mut ids = IdGenerator::from_name("dxf");
+        assert_eq!(
+            read(b"AutoCAD Binary DXF\r\n\x1a\0...", &options, &mut ids),
+            Err(Error::Binary)
+        );
+        assert_eq!(
+            read(b"0\nSECTION\nx\n", &options, &mut ids),
+            Err(Error::Syntax {
+                line: 3,
+                message: "expected a group code, found \"x\"".to_owned()
+            })
+        );
+        let fit_points = dxf(&[(0, "SPLINE"), (71, "3"), (11, "0"), (21, "0")]);
+        let err = read(fit_points.as_bytes(), &options, &mut ids).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "can't draw the SPLINE on line 15: splines defined only by fit points aren't supported"
+        );
+        // A degree 1 spline whose middle knot is repeated twice has a gap in it.
+        let broken = dxf(&[
+            (0, "SPLINE"),
+            (71, "1"),
+            (40, "0"),
+            (40, "0"),
+            (40, "0.5"),
+            (40, "0.5"),
+            (40, "1"),
+            (40, "1"),
+            (10, "0"),
+            (20, "0"),
+            (10, "1"),
+            (20, "0"),
+            (10, "2"),
+            (20, "1"),
+            (10, "3"),
+            (20, "1"),
+        ]);
+        assert!(matches!(
+            read(broken.as_bytes(), &options, &mut ids),
+            Err(Error::UnsupportedEntity { line: 15, .. })
+        ));
+        // A line needs two points.
+        let too_short = dxf(&[
+            (0, "SPLINE"),
+            (71, "1"),
+            (40, "0"),
+            (40, "0"),
+            (40, "0"),
+            (10, "0"),
+            (20, "0"),
+        ]);
+        assert!(matches!(
+            read(too_short.as_bytes(), &options, &mut ids),
+            Err(Error::UnsupportedEntity { line: 15, .. })
+        ));
+    }
+}
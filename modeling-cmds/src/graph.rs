@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{id::ModelingCmdId, ModelingCmd};
+
+/// How a list of modeling commands depend on each other, through the IDs they refer to.
+///
+/// Command B depends on an earlier command A if B refers to an entity which A created,
+/// i.e. A's command ID, or one of A's [created IDs](ModelingCmd::created_ids).
+/// B also has to stay after A if they both refer to the same entity, and either of them
+/// [mutates the scene](ModelingCmd::mutates_scene), because then running them in the other
+/// order could give a different result.
+/// [Queries](ModelingCmd::is_query) read the whole scene, so they stay after every earlier
+/// command which mutates the scene, and before every later one. To keep the graph small,
+/// queries also stay in order with each other.
+///
+/// The graph only knows about IDs. It can't tell that a command depends on state which
+/// isn't identified by an ID, like whether the engine is in sketch mode, or which entities
+/// are left after a `SceneClearAll`.
+#[derive(Debug, Clone)]
+pub struct CommandGraph {
+    nodes: Vec<Node>,
+    dangling: Vec<DanglingReference>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    cmd_id: ModelingCmdId,
+    is_query: bool,
+    /// Earlier commands which created an entity this command refers to.
+    dependencies: Vec<usize>,
+    /// Earlier commands which this command has to stay after, including its dependencies.
+    after: Vec<usize>,
+    /// Later commands which refer to an entity this command created.
+    dependents: Vec<usize>,
+}
+
+/// A command referred to an ID which no earlier command created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingReference {
+    /// Index of the command, in the list the graph was built from.
+    pub index: usize,
+    /// ID of the command.
+    pub cmd_id: ModelingCmdId,
+    /// The ID it referred to.
+    pub referenced_id: Uuid,
+}
+
+impl CommandGraph {
+    /// Analyze these commands, in the order they would be sent.
+    pub fn new<'a>(cmds: impl IntoIterator<Item = (ModelingCmdId, &'a ModelingCmd)>) -> Self {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut dangling = Vec::new();
+        // Which command created each entity.
+        let mut creators: HashMap<Uuid, usize> = HashMap::new();
+        // The last command which referred to each entity and mutated the scene.
+        let mut last_mutation: HashMap<Uuid, usize> = HashMap::new();
+        // Commands which referred to each entity without mutating the scene, since its last mutation.
+        let mut readers: HashMap<Uuid, Vec<usize>> = HashMap::new();
+        // The last query, which stays after every command that mutated the scene before it,
+        // through the commands it has to stay after.
+        let mut last_query = None;
+        // Commands which mutated the scene since the last query.
+        let mut mutations_since_query = Vec::new();
+        for (index, (cmd_id, cmd)) in cmds.into_iter().enumerate() {
+            let mutates_scene = cmd.mutates_scene();
+            let is_query = cmd.is_query();
+            let mut referenced_ids = cmd.referenced_ids();
+            referenced_ids.sort();
+            referenced_ids.dedup();
+            let mut dependencies = Vec::new();
+            let mut after = Vec::new();
+            for referenced_id in referenced_ids {
+                match creators.get(&referenced_id) {
+                    Some(&creator) => dependencies.push(creator),
+                    None => dangling.push(DanglingReference {
+                        index,
+                        cmd_id,
+                        referenced_id,
+                    }),
+                }
+                after.extend(last_mutation.get(&referenced_id));
+                if mutates_scene {
+                    after.extend(readers.remove(&referenced_id).unwrap_or_default());
+                    last_mutation.insert(referenced_id, index);
+                } else {
+                    readers.entry(referenced_id).or_default().push(index);
+                }
+            }
+            if is_query {
+                after.append(&mut mutations_since_query);
+                after.extend(last_query);
+                last_query = Some(index);
+            } else if mutates_scene {
+                after.extend(last_query);
+                mutations_since_query.push(index);
+            }
+            dependencies.sort_unstable();
+            dependencies.dedup();
+            after.extend(&dependencies);
+            after.sort_unstable();
+            after.dedup();
+            for &dependency in &dependencies {
+                nodes[dependency].dependents.push(index);
+            }
+            for created_id in std::iter::once(cmd_id.into()).chain(cmd.created_ids()) {
+                creators.insert(created_id, index);
+            }
+            nodes.push(Node {
+                cmd_id,
+                is_query,
+                dependencies,
+                after,
+                dependents: Vec::new(),
+            });
+        }
+        Self { nodes, dangling }
+    }
+
+    /// How many commands are in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Is the graph empty?
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// ID of the command at this index.
+    pub fn cmd_id(&self, index: usize) -> ModelingCmdId {
+        self.nodes[index].cmd_id
+    }
+
+    /// Indices of the earlier commands which created an entity that this command refers to.
+    pub fn dependencies(&self, index: usize) -> &[usize] {
+        &self.nodes[index].dependencies
+    }
+
+    /// Indices of the later commands which refer to an entity that this command created.
+    pub fn dependents(&self, index: usize) -> &[usize] {
+        &self.nodes[index].dependents
+    }
+
+    /// Every reference to an ID which no earlier command created.
+    /// Either the command is wrong, or it refers to something the engine created by itself,
+    /// like the faces of an extrusion, whose IDs the client got from a query's response.
+    pub fn dangling_references(&self) -> &[DanglingReference] {
+        &self.dangling
+    }
+
+    /// Could these two commands be run in either order, without changing the result?
+    /// That's only true if neither has to stay after the other, directly or through other commands.
+    pub fn can_reorder(&self, a: usize, b: usize) -> bool {
+        let (first, last) = (a.min(b), a.max(b));
+        if first == last {
+            return true;
+        }
+        // Walk back from the later command, through every command it has to stay after.
+        let mut seen = HashSet::new();
+        let mut stack = vec![last];
+        while let Some(index) = stack.pop() {
+            for &earlier in &self.nodes[index].after {
+                if earlier == first {
+                    return false;
+                }
+                // Commands before the first one can't lead back to it.
+                if earlier > first && seen.insert(earlier) {
+                    stack.push(earlier);
+                }
+            }
+        }
+        true
+    }
+
+    /// Indices of the commands which could be dropped without changing the scene:
+    /// they're [queries](ModelingCmd::is_query), and no later command refers to anything they created.
+    /// Other commands which don't mutate the scene, like camera moves or selections, still change
+    /// what the user sees, so they're kept.
+    pub fn droppable(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_query && node.dependents.is_empty())
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        shared::{PathSegment, Point3d},
+        ClosePath, ExtendPath, GetNumObjects, ObjectVisible, StartPath,
+    };
+
+    fn extend(path: ModelingCmdId) -> ModelingCmd {
+        ModelingCmd::ExtendPath(
+            ExtendPath::builder()
+                .path(path)
+                .segment(PathSegment::Line {
+                    end: Point3d::default(),
+                    relative: true,
+                })
+                .build(),
+        )
+    }
+
+    #[test]
+    fn graph() {
+        let path = ModelingCmdId(Uuid::new_v4());
+        let hidden = Uuid::new_v4();
+        let cmds = [
+            (path, ModelingCmd::StartPath(StartPath::default())),
+            (ModelingCmdId(Uuid::new_v4()), extend(path)),
+            (ModelingCmdId(Uuid::new_v4()), extend(path)),
+            (
+                ModelingCmdId(Uuid::new_v4()),
+                ModelingCmd::GetNumObjects(GetNumObjects::default()),
+            ),
+            (
+                ModelingCmdId(Uuid::new_v4()),
+                ModelingCmd::ClosePath(ClosePath::builder().path_id(path.into()).build()),
+            ),
+            (
+                ModelingCmdId(Uuid::new_v4()),
+                ModelingCmd::ObjectVisible(ObjectVisible::builder().object_id(hidden).hidden(true).build()),
+            ),
+        ];
+        let graph = CommandGraph::new(cmds.iter().map(|(id, cmd)| (*id, cmd)));
+        assert_eq!(graph.len(), 6);
+        assert_eq!(graph.dependencies(1), [0]);
+        assert_eq!(graph.dependents(0), [1, 2, 4]);
+
+        // Both segments extend the same path, so their order matters.
+        assert!(!graph.can_reorder(1, 2));
+        // The query reads the scene, so it has to stay between the commands which change it,
+        // but nothing depends on it, so it can be dropped.
+        assert!(!graph.can_reorder(0, 3));
+        assert!(!graph.can_reorder(3, 4));
+        assert!(graph.can_reorder(3, 5));
+        assert_eq!(graph.droppable(), [3]);
+        // Hiding an object nobody created is suspicious.
+        assert_eq!(
+            graph.dangling_references(),
+            [DanglingReference {
+                index: 5,
+                cmd_id: cmds[5].0,
+                referenced_id: hidden,
+            }]
+        );
+    }
+}
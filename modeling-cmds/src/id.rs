@@ -4,7 +4,8 @@ use schemars::JsonSchema;
 use serde::{de, de::Visitor, Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
-/// All commands have unique IDs. These should be randomly generated.
+/// All commands have unique IDs. These should be randomly generated, or generated by an [`IdGenerator`]
+/// if they should be the same every time the program runs.
 #[derive(Debug, Clone, Copy, Hash, Ord, PartialOrd, Eq, PartialEq, JsonSchema, Serialize)]
 #[cfg_attr(test, derive(Default))]
 #[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
@@ -21,10 +22,114 @@ impl AsRef<Uuid> for ModelingCmdId {
 // implement our own serde deserializer for UUID essentially. We are
 // fortunate to have wrapped the UUID type already so we can do this.
 
+/// Generates [`ModelingCmdId`]s which are the same every time a program runs, so that its
+/// commands and the engine's responses can be compared between runs.
+///
+/// Each ID is a UUIDv5, derived from the generator's namespace, and either a counter or a path
+/// (e.g. `"body/fillet"`). Generators with different namespaces never produce the same IDs.
+/// Fork a child generator for each part of a model, so that adding commands to one part
+/// doesn't change the IDs of every later part.
+///
+/// ```
+/// use kittycad_modeling_cmds::id::IdGenerator;
+///
+/// let mut ids = IdGenerator::from_name("my-model");
+/// let path_id = ids.next_id();
+/// // Derive the fillet's extra face IDs from its own generator.
+/// let extra_face_ids: Vec<uuid::Uuid> = ids.fork("fillet").take(3).map(Into::into).collect();
+/// // The same name always gives the same IDs.
+/// assert_eq!(IdGenerator::from_name("my-model").next_id(), path_id);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdGenerator {
+    namespace: Uuid,
+    /// How many IDs the counter has generated.
+    count: u64,
+}
+
+impl IdGenerator {
+    /// The namespace which [`IdGenerator::from_name`] derives namespaces from.
+    /// It's the UUIDv5 of `https://github.com/KittyCAD/modeling-api` in the URL namespace.
+    pub const ROOT_NAMESPACE: Uuid = Uuid::from_u128(0xb23c36c4_9f16_5498_a065_21d2c54ef888);
+
+    /// Generate IDs in this namespace.
+    pub fn new(namespace: Uuid) -> Self {
+        Self { namespace, count: 0 }
+    }
+
+    /// Generate IDs in a namespace named after e.g. the program or model,
+    /// instead of having to pick a namespace UUID.
+    pub fn from_name(name: &str) -> Self {
+        Self::new(Uuid::new_v5(&Self::ROOT_NAMESPACE, name.as_bytes()))
+    }
+
+    /// The namespace this generator's IDs are derived from.
+    pub fn namespace(&self) -> Uuid {
+        self.namespace
+    }
+
+    /// The next ID from this generator's counter.
+    pub fn next_id(&mut self) -> ModelingCmdId {
+        let id = self.derive(b'#', &self.count.to_be_bytes());
+        self.count += 1;
+        id
+    }
+
+    /// The ID for this path. It doesn't depend on the counter, so it's the same however many
+    /// IDs were generated before.
+    pub fn id_for(&self, path: &str) -> ModelingCmdId {
+        self.derive(b'/', path.as_bytes())
+    }
+
+    /// A child generator, with its own namespace and counter, derived from this generator's
+    /// namespace and the given name. The child's IDs don't depend on this generator's counter.
+    pub fn fork(&self, name: &str) -> Self {
+        Self::new(self.derive(b'+', name.as_bytes()).0)
+    }
+
+    /// Derive an ID from the namespace and the given name.
+    /// Each kind of name has its own prefix, so that e.g. a path can't collide with a counter.
+    fn derive(&self, prefix: u8, name: &[u8]) -> ModelingCmdId {
+        let mut bytes = Vec::with_capacity(name.len() + 1);
+        bytes.push(prefix);
+        bytes.extend_from_slice(name);
+        ModelingCmdId(Uuid::new_v5(&self.namespace, &bytes))
+    }
+}
+
+/// An endless stream of IDs from the generator's counter.
+impl Iterator for IdGenerator {
+    type Item = ModelingCmdId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_id())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn id_generator() {
+        let mut ids = IdGenerator::from_name("test");
+        let first = ids.next_id();
+        let second = ids.next_id();
+        assert_ne!(first, second);
+        assert_eq!(first.0.get_version_num(), 5);
+
+        // Generating the same IDs again gives the same results.
+        let mut again = IdGenerator::from_name("test");
+        assert_eq!(again.next_id(), first);
+        assert_eq!(again.id_for("a/b"), ids.id_for("a/b"));
+        // Forks don't depend on the counter, and don't collide with their parent.
+        assert_eq!(again.fork("child"), ids.fork("child"));
+        assert_ne!(ids.fork("child").next_id(), first);
+        assert_ne!(ids.fork("child").namespace(), ids.fork("other").namespace());
+        // Other namespaces give other IDs.
+        assert_ne!(IdGenerator::from_name("other").next_id(), first);
+    }
+
     #[test]
     fn modeling_cmd_id_from_bson() {
         #[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -20,6 +20,9 @@ mod def_enum;
 /// Import and export types.
 pub mod format;
 
+/// How modeling commands depend on each other.
+pub mod graph;
+
 /// Modeling command IDs, used to associated requests and responses.
 /// Also used to construct commands which refer to previous commands.
 pub mod id;
@@ -32,6 +35,9 @@ pub mod length_unit;
 /// When a modeling command is successful, these responses could be returned.
 pub mod ok_response;
 
+/// Evaluating the geometry of paths locally, without the engine.
+pub mod path;
+
 /// Controlling the rendering session.
 pub mod session;
 
@@ -47,6 +53,9 @@ mod traits;
 /// Units of measurement.
 pub mod units;
 
+/// Checking modeling commands before sending them.
+pub mod validate;
+
 /// Types for the WebSocket API.
 #[cfg(feature = "websocket")]
 pub mod websocket;
@@ -1021,3 +1021,35 @@ define_ok_modeling_cmd_response_enum! {
 
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn output_from_response() {
+        let face_id = Uuid::new_v4();
+        let resp = OkModelingCmdResponse::from(output::ClosePath { face_id });
+        let out = output::ClosePath::try_from(resp).unwrap();
+        assert_eq!(out.face_id, face_id);
+    }
+
+    #[test]
+    fn output_from_wrong_response() {
+        let resp = OkModelingCmdResponse::from(output::ClosePath {
+            face_id: Uuid::new_v4(),
+        });
+        let err = output::TakeSnapshot::try_from(resp).unwrap_err();
+        assert!(matches!(err, OkModelingCmdResponse::ClosePath(_)));
+    }
+
+    #[test]
+    fn empty_output_from_empty_response() {
+        // Outputs with no fields can be sent as the `Empty` response.
+        assert!(output::StartPath::try_from(OkModelingCmdResponse::Empty).is_ok());
+        // But outputs with fields can't.
+        assert!(output::ClosePath::try_from(OkModelingCmdResponse::Empty).is_err());
+    }
+}
@@ -0,0 +1,1158 @@
+use std::{collections::HashMap, f64::consts::PI, fmt};
+
+use uuid::Uuid;
+
+use crate::{
+    id::ModelingCmdId,
+    length_unit::LengthUnit,
+    shared::{PathSegment, Point2d, Point3d},
+    ModelingCmd,
+};
+
+/// Rendering paths to SVG.
+pub mod svg;
+
+/// A point, or the difference between two points.
+type Vector = Point3d<f64>;
+
+/// The geometry of one path segment, once it's been placed at the pen position it started from.
+///
+/// Every curve goes from `t = 0` at its start to `t = 1` at its end. Only lines and circular arcs
+/// move at a constant speed, so for other curves `t = 0.5` isn't necessarily halfway along.
+/// Lengths are in the same units as the commands which drew the path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    /// A straight line.
+    Line {
+        /// Where the line starts.
+        start: Point3d<f64>,
+        /// Where the line ends.
+        end: Point3d<f64>,
+    },
+    /// An elliptical arc, which is circular if both axes are perpendicular and the same length.
+    /// Its point at angle θ is `center + x_axis * cos(θ) + y_axis * sin(θ)`.
+    Arc {
+        /// Center of the ellipse.
+        center: Point3d<f64>,
+        /// Vector from the center to the point at angle 0.
+        x_axis: Point3d<f64>,
+        /// Vector from the center to the point at a quarter turn.
+        y_axis: Point3d<f64>,
+        /// Angle where the arc starts, in radians.
+        start_angle: f64,
+        /// How far the arc turns, in radians. Negative if it turns from `y_axis` towards `x_axis`.
+        sweep: f64,
+    },
+    /// A cubic Bézier curve.
+    Bezier {
+        /// The start, both control points, and the end.
+        points: [Point3d<f64>; 4],
+    },
+    /// A conic section, as a rational quadratic Bézier curve.
+    Conic {
+        /// The start, the control point where the start and end tangents meet, and the end.
+        points: [Point3d<f64>; 3],
+        /// Weight of the control point. Less than 1 for an ellipse, 1 for a parabola,
+        /// and more than 1 for a hyperbola.
+        weight: f64,
+    },
+    /// The involute of a circle, which is traced by the end of a string unwinding from it.
+    /// Its point at parameter s is `center + base_radius * (cos(a) + s sin(a), sin(a) - s cos(a))`,
+    /// where `a = s + rotation`.
+    Involute {
+        /// Center of the circle the string unwinds from.
+        center: Point3d<f64>,
+        /// Radius of the circle the string unwinds from.
+        base_radius: f64,
+        /// How far the involute is rotated around its center, in radians.
+        rotation: f64,
+        /// Parameter s where the curve starts.
+        start_param: f64,
+        /// Parameter s where the curve ends.
+        end_param: f64,
+    },
+}
+
+/// The smallest axis-aligned box around some geometry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    /// The corner with the smallest coordinates.
+    pub min: Point3d<f64>,
+    /// The corner with the largest coordinates.
+    pub max: Point3d<f64>,
+}
+
+impl BoundingBox {
+    fn around(point: Vector) -> Self {
+        Self { min: point, max: point }
+    }
+
+    fn include(&mut self, point: Vector) {
+        self.min = Point3d {
+            x: self.min.x.min(point.x),
+            y: self.min.y.min(point.y),
+            z: self.min.z.min(point.z),
+        };
+        self.max = Point3d {
+            x: self.max.x.max(point.x),
+            y: self.max.y.max(point.y),
+            z: self.max.z.max(point.z),
+        };
+    }
+
+    fn union(mut self, other: Self) -> Self {
+        self.include(other.min);
+        self.include(other.max);
+        self
+    }
+}
+
+impl Curve {
+    /// Where the curve starts.
+    pub fn start(&self) -> Point3d<f64> {
+        self.point_at(0.0)
+    }
+
+    /// Where the curve ends.
+    pub fn end(&self) -> Point3d<f64> {
+        self.point_at(1.0)
+    }
+
+    /// The point at parameter `t`, from 0 at the start to 1 at the end.
+    pub fn point_at(&self, t: f64) -> Point3d<f64> {
+        match *self {
+            Self::Line { start, end } => start + (end - start) * t,
+            Self::Arc {
+                center,
+                x_axis,
+                y_axis,
+                start_angle,
+                sweep,
+            } => {
+                let angle = start_angle + sweep * t;
+                center + x_axis * angle.cos() + y_axis * angle.sin()
+            }
+            Self::Bezier {
+                points: [p0, p1, p2, p3],
+            } => {
+                let s = 1.0 - t;
+                p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+            }
+            Self::Conic { .. } => {
+                let (numerator, denominator) = self.conic_terms(t);
+                numerator / denominator
+            }
+            Self::Involute {
+                center,
+                base_radius,
+                rotation,
+                start_param,
+                end_param,
+            } => {
+                let s = lerp(start_param, end_param, t);
+                let angle = s + rotation;
+                let unwound = Point3d {
+                    x: angle.cos() + s * angle.sin(),
+                    y: angle.sin() - s * angle.cos(),
+                    z: 0.0,
+                };
+                center + unwound * base_radius
+            }
+        }
+    }
+
+    /// The derivative with respect to `t`, i.e. the direction and speed the curve moves in at `t`.
+    fn derivative_at(&self, t: f64) -> Vector {
+        match *self {
+            Self::Line { start, end } => end - start,
+            Self::Arc {
+                x_axis,
+                y_axis,
+                start_angle,
+                sweep,
+                ..
+            } => {
+                let angle = start_angle + sweep * t;
+                (y_axis * angle.cos() - x_axis * angle.sin()) * sweep
+            }
+            Self::Bezier {
+                points: [p0, p1, p2, p3],
+            } => {
+                let s = 1.0 - t;
+                (p1 - p0) * (3.0 * s * s) + (p2 - p1) * (6.0 * s * t) + (p3 - p2) * (3.0 * t * t)
+            }
+            Self::Conic { .. } => {
+                let (numerator, denominator) = self.conic_terms(t);
+                let (numerator_derivative, denominator_derivative) = self.conic_derivative_terms(t);
+                (numerator_derivative * denominator - numerator * denominator_derivative) / (denominator * denominator)
+            }
+            Self::Involute {
+                base_radius,
+                rotation,
+                start_param,
+                end_param,
+                ..
+            } => {
+                let s = lerp(start_param, end_param, t);
+                let angle = s + rotation;
+                let direction = Point3d {
+                    x: angle.cos(),
+                    y: angle.sin(),
+                    z: 0.0,
+                };
+                direction * (base_radius * s * (end_param - start_param))
+            }
+        }
+    }
+
+    /// The unit tangent at parameter `t`, pointing the way the curve goes.
+    /// It's zero if the curve has no direction there, e.g. a line from a point to itself.
+    pub fn tangent_at(&self, t: f64) -> Point3d<f64> {
+        if let Self::Involute {
+            rotation,
+            start_param,
+            end_param,
+            ..
+        } = *self
+        {
+            // The involute stops where it leaves its base circle, but its direction is known everywhere.
+            let angle = lerp(start_param, end_param, t) + rotation;
+            let direction = Point3d {
+                x: angle.cos(),
+                y: angle.sin(),
+                z: 0.0,
+            };
+            return direction * (end_param - start_param).signum();
+        }
+        let derivative = self.derivative_at(t);
+        if norm(derivative) > f64::EPSILON {
+            return normalize(derivative);
+        }
+        // The curve stops for a moment here, like a Bézier curve whose control point is on its
+        // end. Its direction is still the direction it
+        // moves in just after (or just before) this point.
+        let step = 1e-6;
+        let (before, after) = if t + step <= 1.0 { (t, t + step) } else { (t - step, t) };
+        normalize(self.point_at(after) - self.point_at(before))
+    }
+
+    /// How long the curve is.
+    /// Lines, circular arcs and involutes are measured exactly, other curves numerically.
+    pub fn length(&self) -> f64 {
+        match *self {
+            Self::Line { start, end } => norm(end - start),
+            Self::Arc {
+                x_axis, y_axis, sweep, ..
+            } if is_circle(x_axis, y_axis) => norm(x_axis) * sweep.abs(),
+            Self::Involute {
+                base_radius,
+                start_param,
+                end_param,
+                ..
+            } => base_radius * (end_param * end_param - start_param * start_param).abs() / 2.0,
+            _ => integrate(|t| norm(self.derivative_at(t))),
+        }
+    }
+
+    /// The smallest box around the curve.
+    pub fn bounding_box(&self) -> BoundingBox {
+        let mut bbox = BoundingBox::around(self.start());
+        bbox.include(self.end());
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            for t in self.extremes(axis) {
+                bbox.include(self.point_at(t));
+            }
+        }
+        bbox
+    }
+
+    /// Parameters between the start and end where the curve stops moving along this axis,
+    /// which is where it might reach its furthest along that axis.
+    fn extremes(&self, axis: Axis) -> Vec<f64> {
+        match *self {
+            Self::Line { .. } => Vec::new(),
+            Self::Arc {
+                x_axis,
+                y_axis,
+                start_angle,
+                sweep,
+                ..
+            } => {
+                // The arc's coordinate is a cos(θ) + b sin(θ), which turns around every half turn
+                // from atan2(b, a).
+                let (a, b) = (axis.of(x_axis), axis.of(y_axis));
+                if a == 0.0 && b == 0.0 {
+                    return Vec::new();
+                }
+                angles_within(b.atan2(a), PI, start_angle, sweep)
+            }
+            Self::Bezier {
+                points: [p0, p1, p2, p3],
+            } => {
+                // The derivative is a quadratic Bézier curve over these differences.
+                let (d0, d1, d2) = (axis.of(p1 - p0), axis.of(p2 - p1), axis.of(p3 - p2));
+                quadratic_roots(d0 - 2.0 * d1 + d2, 2.0 * (d1 - d0), d0)
+            }
+            Self::Conic { .. } => {
+                // The numerator of the coordinate's derivative is a quadratic, so three samples
+                // are enough to find it.
+                let numerator = |t: f64| {
+                    let (n, d) = self.conic_terms(t);
+                    let (dn, dd) = self.conic_derivative_terms(t);
+                    axis.of(dn) * d - axis.of(n) * dd
+                };
+                let (f0, f_half, f1) = (numerator(0.0), numerator(0.5), numerator(1.0));
+                let a = 2.0 * f1 + 2.0 * f0 - 4.0 * f_half;
+                quadratic_roots(a, f1 - f0 - a, f0)
+            }
+            Self::Involute {
+                rotation,
+                start_param,
+                end_param,
+                ..
+            } => {
+                // The involute moves in direction (cos(s + rotation), sin(s + rotation)).
+                let phase = match axis {
+                    Axis::X => PI / 2.0,
+                    Axis::Y => 0.0,
+                    Axis::Z => return Vec::new(),
+                };
+                angles_within(phase - rotation, PI, start_param, end_param - start_param)
+            }
+        }
+    }
+
+    /// Points along the curve, from its start to its end, so that the straight lines between
+    /// them are never further than `tolerance` from the curve.
+    pub fn tessellate(&self, tolerance: f64) -> Vec<Point3d<f64>> {
+        let mut points = vec![self.start()];
+        if let Self::Line { end, .. } = self {
+            points.push(*end);
+            return points;
+        }
+        // Start with a few pieces, so that a curve which crosses back over its chord (like an S)
+        // isn't mistaken for a straight line.
+        const PIECES: u32 = 4;
+        for i in 0..PIECES {
+            let (t0, t1) = (f64::from(i) / f64::from(PIECES), f64::from(i + 1) / f64::from(PIECES));
+            self.subdivide(t0, self.point_at(t0), t1, self.point_at(t1), tolerance, 0, &mut points);
+        }
+        points
+    }
+
+    /// Add points to approximate the curve between `t0` and `t1` (not including `t0`'s point).
+    #[allow(clippy::too_many_arguments)]
+    fn subdivide(
+        &self,
+        t0: f64,
+        p0: Vector,
+        t1: f64,
+        p1: Vector,
+        tolerance: f64,
+        depth: u32,
+        points: &mut Vec<Vector>,
+    ) {
+        // Each level halves the piece, so this is plenty for any sensible tolerance,
+        // and stops a tolerance of zero from recursing forever.
+        const MAX_DEPTH: u32 = 16;
+        let t = (t0 + t1) / 2.0;
+        let p = self.point_at(t);
+        if depth < MAX_DEPTH && distance_to_segment(p, p0, p1) > tolerance {
+            self.subdivide(t0, p0, t, p, tolerance, depth + 1, points);
+            self.subdivide(t, p, t1, p1, tolerance, depth + 1, points);
+        } else {
+            points.push(p1);
+        }
+    }
+
+    /// The numerator and denominator of a conic's point at `t`.
+    fn conic_terms(&self, t: f64) -> (Vector, f64) {
+        let Self::Conic {
+            points: [p0, p1, p2],
+            weight,
+        } = *self
+        else {
+            unreachable!("only conics have conic terms");
+        };
+        let s = 1.0 - t;
+        let (b0, b1, b2) = (s * s, 2.0 * s * t * weight, t * t);
+        (p0 * b0 + p1 * b1 + p2 * b2, b0 + b1 + b2)
+    }
+
+    /// The derivatives of [`Curve::conic_terms`] with respect to `t`.
+    fn conic_derivative_terms(&self, t: f64) -> (Vector, f64) {
+        let Self::Conic {
+            points: [p0, p1, p2],
+            weight,
+        } = *self
+        else {
+            unreachable!("only conics have conic terms");
+        };
+        let (b0, b1, b2) = (-2.0 * (1.0 - t), (2.0 - 4.0 * t) * weight, 2.0 * t);
+        (p0 * b0 + p1 * b1 + p2 * b2, b0 + b1 + b2)
+    }
+}
+
+/// One coordinate axis.
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    fn of(self, v: Vector) -> f64 {
+        match self {
+            Self::X => v.x,
+            Self::Y => v.y,
+            Self::Z => v.z,
+        }
+    }
+}
+
+/// A path being drawn by modeling commands, tracked locally so its geometry can be evaluated
+/// without asking the engine.
+///
+/// Like in the engine, the pen starts at the origin, each segment starts where the pen is,
+/// and the pen moves to the end of each segment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    pen: Vector,
+    /// Where the first segment started, which is where closing the path returns to.
+    start: Option<Vector>,
+    segments: Vec<Curve>,
+}
+
+impl Path {
+    /// An empty path, with its pen at the origin.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the pen is, i.e. where the next segment will start.
+    pub fn pen(&self) -> Point3d<f64> {
+        self.pen
+    }
+
+    /// Each segment's geometry, in the order they were drawn.
+    pub fn segments(&self) -> &[Curve] {
+        &self.segments
+    }
+
+    /// Move the pen without drawing anything, like `MovePathPen`.
+    pub fn move_pen(&mut self, to: Point3d<LengthUnit>) {
+        self.pen = to.map(|l| l.0);
+    }
+
+    /// Draw a segment from the pen, like `ExtendPath`, and move the pen to the end of it.
+    ///
+    /// Segments are drawn in the plane of the pen, except for lines, Bézier curves and arcs
+    /// through 3 points, which can go anywhere. Segments whose points don't define their curve,
+    /// e.g. an arc through 3 points on a line, are drawn as a straight line to their end.
+    /// Like in KCL, `TangentialArcTo` ends at an offset from the pen, and its angle snapping
+    /// isn't supported.
+    pub fn extend(&mut self, segment: &PathSegment) -> &Curve {
+        let curve = self.curve(segment);
+        self.push(curve)
+    }
+
+    /// Draw a straight line back to where the path started, like `ClosePath`.
+    /// Does nothing if the pen is already there.
+    pub fn close(&mut self) {
+        if let Some(start) = self.start {
+            if norm(start - self.pen) > f64::EPSILON {
+                self.push(Curve::Line {
+                    start: self.pen,
+                    end: start,
+                });
+            }
+        }
+    }
+
+    fn push(&mut self, curve: Curve) -> &Curve {
+        self.start.get_or_insert(self.pen);
+        self.pen = curve.end();
+        self.segments.push(curve);
+        self.segments.last().unwrap()
+    }
+
+    /// The direction the path goes in at the pen, in the pen's plane.
+    /// Before any segments are drawn, that's along the x-axis.
+    fn pen_direction(&self) -> Vector {
+        let direction = match self.segments.last() {
+            Some(last) => last.tangent_at(1.0),
+            None => X,
+        };
+        let direction = Point3d { z: 0.0, ..direction };
+        if norm(direction) > f64::EPSILON {
+            normalize(direction)
+        } else {
+            X
+        }
+    }
+
+    fn curve(&self, segment: &PathSegment) -> Curve {
+        let pen = self.pen;
+        // Where relative points are relative to.
+        let origin = |relative: bool| if relative { pen } else { Vector::default() };
+        let flat = |p: Point2d<LengthUnit>| Point3d {
+            x: p.x.0,
+            y: p.y.0,
+            z: pen.z,
+        };
+        match *segment {
+            PathSegment::Line { end, relative } => Curve::Line {
+                start: pen,
+                end: origin(relative) + end.map(|l| l.0),
+            },
+            PathSegment::Arc {
+                center,
+                radius,
+                start,
+                end,
+                relative,
+            } => {
+                let center = flat(center)
+                    + Point3d {
+                        z: 0.0,
+                        ..origin(relative)
+                    };
+                let (start, end) = (start.to_radians(), end.to_radians());
+                Curve::Arc {
+                    center,
+                    x_axis: X * radius.0,
+                    y_axis: Y * radius.0,
+                    start_angle: start,
+                    sweep: end - start,
+                }
+            }
+            PathSegment::Bezier {
+                control1,
+                control2,
+                end,
+                relative,
+            } => {
+                let origin = origin(relative);
+                Curve::Bezier {
+                    points: [
+                        pen,
+                        origin + control1.map(|l| l.0),
+                        origin + control2.map(|l| l.0),
+                        origin + end.map(|l| l.0),
+                    ],
+                }
+            }
+            PathSegment::TangentialArc { radius, offset } => {
+                let direction = self.pen_direction();
+                let offset = offset.to_radians();
+                // The center is to the left when turning anticlockwise, or to the right when
+                // turning clockwise, so the arc leaves the pen in the path's direction.
+                let left = perpendicular(direction);
+                let side = if offset >= 0.0 { left } else { left * -1.0 };
+                let center = pen + side * radius.0;
+                let from_center = pen - center;
+                Curve::Arc {
+                    center,
+                    x_axis: X * radius.0,
+                    y_axis: Y * radius.0,
+                    start_angle: from_center.y.atan2(from_center.x),
+                    sweep: offset,
+                }
+            }
+            PathSegment::TangentialArcTo { to, .. } => {
+                let direction = self.pen_direction();
+                let offset = Point3d {
+                    z: 0.0,
+                    ..to.map(|l| l.0)
+                };
+                let end = pen + offset;
+                let left = perpendicular(direction);
+                let sideways = dot(offset, left);
+                if sideways.abs() <= f64::EPSILON * norm(offset) {
+                    return Curve::Line { start: pen, end };
+                }
+                // The center is on the line through the pen perpendicular to its direction,
+                // equally far from the pen and the end.
+                let signed_radius = dot(offset, offset) / (2.0 * sideways);
+                let center = pen + left * signed_radius;
+                let radius = signed_radius.abs();
+                let (from, to) = (pen - center, end - center);
+                let start_angle = from.y.atan2(from.x);
+                let turn = (to.y.atan2(to.x) - start_angle).rem_euclid(2.0 * PI);
+                let sweep = if signed_radius > 0.0 { turn } else { turn - 2.0 * PI };
+                Curve::Arc {
+                    center,
+                    x_axis: X * radius,
+                    y_axis: Y * radius,
+                    start_angle,
+                    sweep,
+                }
+            }
+            PathSegment::ArcTo {
+                interior,
+                end,
+                relative,
+            } => {
+                let origin = origin(relative);
+                three_point_arc(pen, origin + interior.map(|l| l.0), origin + end.map(|l| l.0))
+            }
+            PathSegment::CircularInvolute {
+                start_radius,
+                end_radius,
+                angle,
+                reverse,
+            } => {
+                let base_radius = start_radius.0;
+                let ratio = end_radius.0 / base_radius;
+                let end_param = if ratio > 1.0 { (ratio * ratio - 1.0).sqrt() } else { 0.0 };
+                let (start_param, end_param) = if reverse { (end_param, 0.0) } else { (0.0, end_param) };
+                let rotation = angle.to_radians();
+                // Place the involute so it starts at the pen.
+                let unplaced = Curve::Involute {
+                    center: Vector::default(),
+                    base_radius,
+                    rotation,
+                    start_param,
+                    end_param,
+                };
+                Curve::Involute {
+                    center: pen - unplaced.start(),
+                    base_radius,
+                    rotation,
+                    start_param,
+                    end_param,
+                }
+            }
+            PathSegment::Ellipse {
+                center,
+                major_axis,
+                minor_radius,
+                start_angle,
+                end_angle,
+            } => {
+                let x_axis = major_axis.map(|l| l.0).with_z(0.0);
+                let (start, end) = (start_angle.to_radians(), end_angle.to_radians());
+                Curve::Arc {
+                    center: flat(center),
+                    x_axis,
+                    y_axis: normalize(perpendicular(x_axis)) * minor_radius.0,
+                    start_angle: start,
+                    sweep: end - start,
+                }
+            }
+            PathSegment::ConicTo {
+                interior,
+                end,
+                start_tangent,
+                end_tangent,
+                relative,
+            } => {
+                let origin = Point3d {
+                    z: 0.0,
+                    ..origin(relative)
+                };
+                conic(
+                    pen,
+                    origin + flat(interior),
+                    origin + flat(end),
+                    start_tangent.map(|l| l.0),
+                    end_tangent.map(|l| l.0),
+                )
+            }
+        }
+    }
+
+    /// How long the whole path is.
+    pub fn length(&self) -> f64 {
+        self.segments.iter().map(Curve::length).sum()
+    }
+
+    /// The point at parameter `t` along the whole path, from 0 at its start to 1 at its end.
+    /// Each segment gets an equal share of `t`, however long it is.
+    /// `None` if the path has no segments.
+    pub fn point_at(&self, t: f64) -> Option<Point3d<f64>> {
+        let (segment, t) = self.locate(t)?;
+        Some(segment.point_at(t))
+    }
+
+    /// The unit tangent at parameter `t` along the whole path, like [`Path::point_at`].
+    pub fn tangent_at(&self, t: f64) -> Option<Point3d<f64>> {
+        let (segment, t) = self.locate(t)?;
+        Some(segment.tangent_at(t))
+    }
+
+    /// Which segment parameter `t` along the whole path falls in, and where in that segment.
+    fn locate(&self, t: f64) -> Option<(&Curve, f64)> {
+        let count = self.segments.len();
+        if count == 0 {
+            return None;
+        }
+        let scaled = t.clamp(0.0, 1.0) * count as f64;
+        let index = (scaled.floor() as usize).min(count - 1);
+        Some((&self.segments[index], scaled - index as f64))
+    }
+
+    /// The smallest box around every segment. `None` if the path has no segments.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.segments.iter().map(Curve::bounding_box).reduce(BoundingBox::union)
+    }
+
+    /// Polylines which are never further than `tolerance` from the path.
+    /// There's one polyline for each run of connected segments, so a path whose pen was moved
+    /// between segments has more than one.
+    pub fn tessellate(&self, tolerance: f64) -> Vec<Vec<Point3d<f64>>> {
+        let mut polylines: Vec<Vec<Vector>> = Vec::new();
+        for segment in &self.segments {
+            let mut points = segment.tessellate(tolerance);
+            match polylines.last_mut() {
+                Some(polyline) if polyline.last().is_some_and(|&last| norm(last - points[0]) <= tolerance) => {
+                    polyline.extend(points.drain(1..));
+                }
+                _ => polylines.push(points),
+            }
+        }
+        polylines
+    }
+}
+
+/// Every path drawn by a list of modeling commands, so their geometry can be evaluated locally.
+#[derive(Debug, Clone, Default)]
+pub struct Paths {
+    /// Each path, in the order they were started.
+    paths: Vec<(Uuid, Path)>,
+    /// Where each path is in `paths`.
+    indices: HashMap<Uuid, usize>,
+}
+
+impl Paths {
+    /// No paths yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow a command, if it draws a path: `StartPath`, `MovePathPen`, `ExtendPath` or `ClosePath`.
+    /// Other commands are ignored.
+    pub fn apply(&mut self, cmd_id: ModelingCmdId, cmd: &ModelingCmd) -> Result<(), UnknownPath> {
+        match cmd {
+            ModelingCmd::StartPath(_) => {
+                let path_id = cmd_id.into();
+                match self.indices.get(&path_id) {
+                    // Starting a path again replaces it.
+                    Some(&index) => self.paths[index].1 = Path::new(),
+                    None => {
+                        self.indices.insert(path_id, self.paths.len());
+                        self.paths.push((path_id, Path::new()));
+                    }
+                }
+            }
+            ModelingCmd::MovePathPen(cmd) => self.get_mut(cmd.path.into())?.move_pen(cmd.to),
+            ModelingCmd::ExtendPath(cmd) => {
+                self.get_mut(cmd.path.into())?.extend(&cmd.segment);
+            }
+            ModelingCmd::ClosePath(cmd) => self.get_mut(cmd.path_id)?.close(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The path started by the `StartPath` command with this ID.
+    pub fn get(&self, path_id: Uuid) -> Option<&Path> {
+        self.indices.get(&path_id).map(|&index| &self.paths[index].1)
+    }
+
+    /// Every path, by the ID of the `StartPath` command which started it, in the order they were started.
+    pub fn iter(&self) -> impl Iterator<Item = (Uuid, &Path)> {
+        self.paths.iter().map(|(id, path)| (*id, path))
+    }
+
+    fn get_mut(&mut self, path_id: Uuid) -> Result<&mut Path, UnknownPath> {
+        match self.indices.get(&path_id) {
+            Some(&index) => Ok(&mut self.paths[index].1),
+            None => Err(UnknownPath { path_id }),
+        }
+    }
+}
+
+/// A command drew on a path which no earlier `StartPath` command started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownPath {
+    /// The path the command drew on.
+    pub path_id: Uuid,
+}
+
+impl fmt::Display for UnknownPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no path was started with ID {}", self.path_id)
+    }
+}
+
+impl std::error::Error for UnknownPath {}
+
+const X: Vector = Point3d { x: 1.0, y: 0.0, z: 0.0 };
+const Y: Vector = Point3d { x: 0.0, y: 1.0, z: 0.0 };
+
+/// The arc from `start`, through `interior`, to `end`.
+/// A straight line if they're on a line, because then no circle goes through them.
+fn three_point_arc(start: Vector, interior: Vector, end: Vector) -> Curve {
+    let (ab, ac) = (interior - start, end - start);
+    let normal = cross(ab, ac);
+    let normal_squared = dot(normal, normal);
+    if normal_squared <= f64::EPSILON * dot(ab, ab) * dot(ac, ac) {
+        return Curve::Line { start, end };
+    }
+    let center = start + (cross(normal, ab) * dot(ac, ac) + cross(ac, normal) * dot(ab, ab)) / (2.0 * normal_squared);
+    let from_center = start - center;
+    let radius = norm(from_center);
+    // Going from start to interior to end turns anticlockwise around the normal.
+    let x_axis = from_center;
+    let y_axis = normalize(cross(normal, x_axis)) * radius;
+    let to_end = end - center;
+    let sweep = dot(to_end, y_axis).atan2(dot(to_end, x_axis)).rem_euclid(2.0 * PI);
+    Curve::Arc {
+        center,
+        x_axis,
+        y_axis,
+        start_angle: 0.0,
+        sweep,
+    }
+}
+
+/// The conic from `start` to `end` with these tangents there, which goes through `interior`.
+/// If no conic does, the arc through those three points.
+fn conic(
+    start: Vector,
+    interior: Vector,
+    end: Vector,
+    start_tangent: Point2d<f64>,
+    end_tangent: Point2d<f64>,
+) -> Curve {
+    let (t0, t1) = (start_tangent.with_z(0.0), end_tangent.with_z(0.0));
+    // The control point is where the tangents meet: start + a * t0 == end + b * t1.
+    let denominator = cross2(t1, t0);
+    if denominator.abs() <= f64::EPSILON * norm(t0) * norm(t1) {
+        return three_point_arc(start, interior, end);
+    }
+    let a = cross2(t1, end - start) / denominator;
+    let control = start + t0 * a;
+    // A rational quadratic Bézier curve's point is a weighted average of its three points,
+    // whose weights (u, v, w) always have v² / (u w) = 4 weight².
+    let area = cross2(control - start, end - start);
+    if area.abs() <= f64::EPSILON {
+        return three_point_arc(start, interior, end);
+    }
+    let v = cross2(interior - start, end - start) / area;
+    let w = cross2(control - start, interior - start) / area;
+    let u = 1.0 - v - w;
+    if u <= 0.0 || v <= 0.0 || w <= 0.0 {
+        // The interior point isn't between the start, control point and end, so it can't be on the conic.
+        return three_point_arc(start, interior, end);
+    }
+    Curve::Conic {
+        points: [start, control, end],
+        weight: v / (2.0 * (u * w).sqrt()),
+    }
+}
+
+/// Solutions to a t² + b t + c = 0 between 0 and 1.
+fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let roots = if a.abs() <= f64::EPSILON * (b.abs() + c.abs()) {
+        if b == 0.0 {
+            Vec::new()
+        } else {
+            vec![-c / b]
+        }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            Vec::new()
+        } else {
+            let root = discriminant.sqrt();
+            vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        }
+    };
+    roots.into_iter().filter(|t| (0.0..=1.0).contains(t)).collect()
+}
+
+/// Parameters `t` between 0 and 1 where `start + sweep * t` is `first` plus some multiple of `period`.
+fn angles_within(first: f64, period: f64, start: f64, sweep: f64) -> Vec<f64> {
+    if sweep == 0.0 {
+        return Vec::new();
+    }
+    let (low, high) = (start.min(start + sweep), start.max(start + sweep));
+    let mut angle = first + ((low - first) / period).ceil() * period;
+    let mut params = Vec::new();
+    while angle <= high {
+        params.push((angle - start) / sweep);
+        angle += period;
+    }
+    params
+}
+
+/// Integrate over t from 0 to 1, with Gauss-Legendre quadrature on many small pieces.
+fn integrate(f: impl Fn(f64) -> f64) -> f64 {
+    const PIECES: u32 = 32;
+    const NODES: [(f64, f64); 5] = [
+        (0.0, 0.568_888_888_888_888_9),
+        (-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+        (0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+        (-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+        (0.906_179_845_938_664, 0.236_926_885_056_189_1),
+    ];
+    let width = 1.0 / f64::from(PIECES);
+    (0..PIECES)
+        .map(|i| {
+            let middle = (f64::from(i) + 0.5) * width;
+            NODES
+                .iter()
+                .map(|(node, weight)| weight * f(middle + node * width / 2.0))
+                .sum::<f64>()
+                * width
+                / 2.0
+        })
+        .sum()
+}
+
+/// Are these the axes of a circle, rather than an ellipse?
+fn is_circle(x_axis: Vector, y_axis: Vector) -> bool {
+    let (x, y) = (norm(x_axis), norm(y_axis));
+    let tolerance = 1e-12 * x.max(y);
+    (x - y).abs() <= tolerance && dot(x_axis, y_axis).abs() <= tolerance * x.max(y)
+}
+
+fn distance_to_segment(point: Vector, start: Vector, end: Vector) -> f64 {
+    let along = end - start;
+    let length_squared = dot(along, along);
+    if length_squared == 0.0 {
+        return norm(point - start);
+    }
+    let t = (dot(point - start, along) / length_squared).clamp(0.0, 1.0);
+    norm(point - (start + along * t))
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+fn cross(a: Vector, b: Vector) -> Vector {
+    Point3d {
+        x: a.y * b.z - a.z * b.y,
+        y: a.z * b.x - a.x * b.z,
+        z: a.x * b.y - a.y * b.x,
+    }
+}
+
+/// The z component of the cross product, for vectors in the XY plane.
+fn cross2(a: Vector, b: Vector) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Rotate a quarter turn anticlockwise around the z-axis.
+fn perpendicular(v: Vector) -> Vector {
+    Point3d {
+        x: -v.y,
+        y: v.x,
+        z: 0.0,
+    }
+}
+
+fn norm(v: Vector) -> f64 {
+    dot(v, v).sqrt()
+}
+
+fn normalize(v: Vector) -> Vector {
+    let length = norm(v);
+    if length == 0.0 {
+        v
+    } else {
+        v / length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{length_unit::LengthUnit, shared::Angle, ClosePath, ExtendPath, MovePathPen, StartPath};
+
+    fn point(x: f64, y: f64) -> Point3d<LengthUnit> {
+        Point3d {
+            x: LengthUnit(x),
+            y: LengthUnit(y),
+            z: LengthUnit(0.0),
+        }
+    }
+
+    fn point2(x: f64, y: f64) -> Point2d<LengthUnit> {
+        Point2d {
+            x: LengthUnit(x),
+            y: LengthUnit(y),
+        }
+    }
+
+    fn assert_near(actual: Vector, expected: (f64, f64)) {
+        assert!(
+            (actual.x - expected.0).abs() < 1e-9 && (actual.y - expected.1).abs() < 1e-9,
+            "expected {expected:?}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn lines_and_arcs() {
+        let mut path = Path::new();
+        path.move_pen(point(1.0, 0.0));
+        path.extend(&PathSegment::Line {
+            end: point(2.0, 0.0),
+            relative: true,
+        });
+        assert_near(path.pen(), (3.0, 0.0));
+        // Turn left, around a circle whose center is above the pen.
+        let arc = *path.extend(&PathSegment::TangentialArc {
+            radius: LengthUnit(1.0),
+            offset: Angle::quarter_circle(),
+        });
+        assert_near(path.pen(), (4.0, 1.0));
+        assert_near(arc.tangent_at(1.0), (0.0, 1.0));
+        assert!((arc.length() - PI / 2.0).abs() < 1e-12);
+        // Turn right, back to the x-axis.
+        path.extend(&PathSegment::TangentialArcTo {
+            to: point(1.0, 1.0),
+            angle_snap_increment: None,
+        });
+        assert_near(path.pen(), (5.0, 2.0));
+        assert_near(path.tangent_at(1.0).unwrap(), (1.0, 0.0));
+        path.extend(&PathSegment::ArcTo {
+            interior: point(1.0, 1.0),
+            end: point(2.0, 0.0),
+            relative: true,
+        });
+        assert_near(path.pen(), (7.0, 2.0));
+        assert_near(path.segments()[3].point_at(0.5), (6.0, 3.0));
+        path.close();
+        assert_near(path.pen(), (1.0, 0.0));
+        assert_eq!(path.segments().len(), 5);
+
+        let bbox = path.bounding_box().unwrap();
+        assert_near(bbox.min, (1.0, 0.0));
+        assert_near(bbox.max, (7.0, 3.0));
+        assert_eq!(path.tessellate(0.01).len(), 1);
+    }
+
+    #[test]
+    fn tessellation_tolerance() {
+        let mut path = Path::new();
+        let circle = *path.extend(&PathSegment::Arc {
+            center: point2(0.0, 0.0),
+            radius: LengthUnit(2.0),
+            start: Angle::zero(),
+            end: Angle::turn(),
+            relative: false,
+        });
+        assert!((circle.length() - 4.0 * PI).abs() < 1e-12);
+        let bbox = circle.bounding_box();
+        assert_near(bbox.min, (-2.0, -2.0));
+        assert_near(bbox.max, (2.0, 2.0));
+
+        let tolerance = 0.01;
+        let points = circle.tessellate(tolerance);
+        assert_near(points[0], (2.0, 0.0));
+        assert_near(*points.last().unwrap(), (2.0, 0.0));
+        // The middle of each chord is the furthest it gets from the circle.
+        for pair in points.windows(2) {
+            let middle = (pair[0] + pair[1]) / 2.0;
+            assert!(2.0 - norm(middle) <= tolerance);
+        }
+        // But it doesn't use many more points than it needs to.
+        let needed = (PI / (1.0 - tolerance / 2.0).acos()).ceil() as usize;
+        assert!(points.len() <= 2 * needed + 1, "{} points", points.len());
+    }
+
+    #[test]
+    fn curves() {
+        let bezier = Curve::Bezier {
+            points: [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)].map(|(x, y)| Point3d { x, y, z: 0.0 }),
+        };
+        assert_near(bezier.bounding_box().max, (1.0, 0.75));
+        let numeric = integrate(|t| norm(bezier.derivative_at(t)));
+        assert!((bezier.length() - numeric).abs() < 1e-12);
+
+        // A parabola, so the weight is 1.
+        let mut path = Path::new();
+        let conic = *path.extend(&PathSegment::ConicTo {
+            interior: point2(1.0, 0.5),
+            end: point2(2.0, 0.0),
+            start_tangent: point2(1.0, 1.0),
+            end_tangent: point2(1.0, -1.0),
+            relative: false,
+        });
+        let Curve::Conic { weight, .. } = conic else {
+            panic!("expected a conic, got {conic:?}");
+        };
+        assert!((weight - 1.0).abs() < 1e-12);
+        assert_near(conic.bounding_box().max, (2.0, 0.5));
+
+        // An involute from radius 1 to 2 unwinds √3 radians of string.
+        let mut path = Path::new();
+        let involute = *path.extend(&PathSegment::CircularInvolute {
+            start_radius: LengthUnit(1.0),
+            end_radius: LengthUnit(2.0),
+            angle: Angle::zero(),
+            reverse: false,
+        });
+        assert_near(involute.start(), (0.0, 0.0));
+        assert_near(involute.tangent_at(0.0), (1.0, 0.0));
+        assert!((involute.length() - 1.5).abs() < 1e-12);
+        let numeric = integrate(|t| norm(involute.derivative_at(t)));
+        assert!((involute.length() - numeric).abs() < 1e-9);
+        // Its end is 2 from the center of the base circle, which is 1 to the left of its start.
+        assert!(
+            (norm(
+                involute.end()
+                    - Point3d {
+                        x: -1.0,
+                        y: 0.0,
+                        z: 0.0
+                    }
+            ) - 2.0)
+                .abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn paths() {
+        let path_id = ModelingCmdId(Uuid::new_v4());
+        let mut paths = Paths::new();
+        let cmds = [
+            ModelingCmd::StartPath(StartPath::default()),
+            ModelingCmd::MovePathPen(MovePathPen::builder().path(path_id).to(point(1.0, 1.0)).build()),
+            ModelingCmd::ExtendPath(
+                ExtendPath::builder()
+                    .path(path_id)
+                    .segment(PathSegment::Line {
+                        end: point(2.0, 1.0),
+                        relative: false,
+                    })
+                    .build(),
+            ),
+            ModelingCmd::ClosePath(ClosePath::builder().path_id(path_id.into()).build()),
+        ];
+        for cmd in &cmds {
+            paths.apply(path_id, cmd).unwrap();
+        }
+        let path = paths.get(path_id.into()).unwrap();
+        assert_eq!(path.segments().len(), 2);
+        assert!((path.length() - 2.0).abs() < 1e-12);
+        assert_near(path.point_at(0.25).unwrap(), (1.5, 1.0));
+
+        let other = Uuid::new_v4();
+        let cmd = ModelingCmd::ClosePath(ClosePath::builder().path_id(other).build());
+        assert_eq!(
+            paths.apply(ModelingCmdId(Uuid::new_v4()), &cmd),
+            Err(UnknownPath { path_id: other })
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::f64::consts::PI;
+
+use super::{cross2, dot, norm, BoundingBox, Curve, Path, Vector};
+use crate::units::UnitLength;
+
+/// How to draw paths in an SVG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgStyle {
+    /// Color of the lines, as any SVG color, e.g. `black` or `#29ffa4`.
+    pub stroke: String,
+    /// Width of the lines, in millimeters.
+    pub stroke_width: f64,
+    /// Color to fill the paths with, or `None` to leave them empty.
+    pub fill: Option<String>,
+    /// Space around the paths, in millimeters.
+    pub margin: f64,
+    /// Involutes, conics and arcs which don't lie flat aren't SVG shapes, so they're drawn as
+    /// straight lines, which are never further than this from the real curve. In millimeters.
+    pub tolerance: f64,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        Self {
+            stroke: "black".to_owned(),
+            stroke_width: 0.25,
+            fill: None,
+            margin: 1.0,
+            tolerance: 0.01,
+        }
+    }
+}
+
+/// Draw these paths as an SVG document, looking down on the XY plane.
+///
+/// The paths' lengths are in `unit`, which sets the size of the document, so that a sketch
+/// drawn in inches is printed at its real size. SVG's y-axis points down, so the paths are
+/// flipped to keep the sketch's y-axis pointing up.
+/// Each path becomes one SVG `<path>`. Lines, Bézier curves and arcs (including elliptical ones)
+/// become the matching SVG path commands, and other curves become straight lines.
+pub fn render<'a>(paths: impl IntoIterator<Item = &'a Path>, unit: UnitLength, style: Option<&SvgStyle>) -> String {
+    let default_style = SvgStyle::default();
+    let style = style.unwrap_or(&default_style);
+    let from_mm = |mm: f64| UnitLength::Millimeters.convert_to(unit, mm);
+    let tolerance = from_mm(style.tolerance);
+    let paths: Vec<&Path> = paths.into_iter().filter(|path| !path.segments().is_empty()).collect();
+
+    let margin = from_mm(style.margin);
+    let (x, y, width, height) = match paths
+        .iter()
+        .filter_map(|path| path.bounding_box())
+        .reduce(BoundingBox::union)
+    {
+        Some(BoundingBox { min, max }) => (
+            min.x - margin,
+            -max.y - margin,
+            max.x - min.x + 2.0 * margin,
+            max.y - min.y + 2.0 * margin,
+        ),
+        None => (0.0, 0.0, 0.0, 0.0),
+    };
+    let to_mm = |length: f64| unit.convert_to(UnitLength::Millimeters, length);
+
+    let mut svg = vec![format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}mm" height="{}mm" viewBox="{} {} {} {}">"#,
+        number(to_mm(width)),
+        number(to_mm(height)),
+        number(x),
+        number(y),
+        number(width),
+        number(height),
+    )];
+    let fill = style.fill.as_deref().unwrap_or("none");
+    for path in paths {
+        svg.push(format!(
+            r#"<path d="{}" fill="{}" stroke="{}" stroke-width="{}" stroke-linejoin="round"/>"#,
+            path_data(path, tolerance),
+            escape(fill),
+            escape(&style.stroke),
+            number(from_mm(style.stroke_width)),
+        ));
+    }
+    svg.push("</svg>\n".to_owned());
+    svg.join("\n")
+}
+
+/// The SVG path commands which draw a path.
+fn path_data(path: &Path, tolerance: f64) -> String {
+    let mut commands = Vec::new();
+    // Where the current run of connected segments started, and where the last one ended.
+    let mut run: Option<(Vector, Vector)> = None;
+    for segment in path.segments() {
+        let start = segment.start();
+        match run {
+            Some((_, end)) if is_near(end, start) => {}
+            _ => {
+                close(run, &mut commands);
+                commands.push(format!("M {}", point(start)));
+                run = Some((start, start));
+            }
+        }
+        draw(segment, tolerance, &mut commands);
+        run = run.map(|(run_start, _)| (run_start, segment.end()));
+    }
+    close(run, &mut commands);
+    commands.join(" ")
+}
+
+/// Close this run of segments, if it ended where it started.
+fn close(run: Option<(Vector, Vector)>, commands: &mut Vec<String>) {
+    if let Some((start, end)) = run {
+        if is_near(start, end) {
+            commands.push("Z".to_owned());
+        }
+    }
+}
+
+/// The SVG path commands which draw a segment, from where the last one ended.
+fn draw(segment: &Curve, tolerance: f64, commands: &mut Vec<String>) {
+    match *segment {
+        Curve::Line { end, .. } => commands.push(format!("L {}", point(end))),
+        Curve::Bezier {
+            points: [_, control1, control2, end],
+        } => commands.push(format!("C {} {} {}", point(control1), point(control2), point(end))),
+        Curve::Arc {
+            x_axis, y_axis, sweep, ..
+        } if is_flat_ellipse(x_axis, y_axis) => {
+            // An SVG arc can't be a whole ellipse, because it would end where it started,
+            // so whole ellipses are drawn in halves.
+            let pieces = if sweep.abs() >= 2.0 * PI - 1e-9 { 2 } else { 1 };
+            let rotation = -x_axis.y.atan2(x_axis.x).to_degrees();
+            let large_arc = u8::from(sweep.abs() / f64::from(pieces) > PI);
+            // The y-axis is flipped, so anticlockwise arcs in the sketch go clockwise in the
+            // SVG, which is SVG's positive direction.
+            let sweep_flag = u8::from(cross2(x_axis, y_axis) * sweep > 0.0);
+            for piece in 1..=pieces {
+                let end = segment.point_at(f64::from(piece) / f64::from(pieces));
+                commands.push(format!(
+                    "A {} {} {} {large_arc} {sweep_flag} {}",
+                    number(norm(x_axis)),
+                    number(norm(y_axis)),
+                    number(rotation),
+                    point(end),
+                ));
+            }
+        }
+        _ => {
+            for p in segment.tessellate(tolerance).into_iter().skip(1) {
+                commands.push(format!("L {}", point(p)));
+            }
+        }
+    }
+}
+
+/// Does an ellipse with these axes lie in the XY plane, so it looks like an ellipse from above?
+/// Its axes must be perpendicular, because SVG arcs are described by their radii.
+fn is_flat_ellipse(x_axis: Vector, y_axis: Vector) -> bool {
+    let scale = norm(x_axis) * norm(y_axis);
+    x_axis.z == 0.0 && y_axis.z == 0.0 && dot(x_axis, y_axis).abs() <= 1e-12 * scale
+}
+
+fn is_near(a: Vector, b: Vector) -> bool {
+    norm(a - b) <= 1e-9 * (1.0 + norm(a))
+}
+
+/// A point in SVG coordinates, whose y-axis points down.
+fn point(p: Vector) -> String {
+    format!("{} {}", number(p.x), number(-p.y))
+}
+
+/// Round a number enough to hide floating-point noise, so the SVG stays readable and diffable.
+fn number(value: f64) -> String {
+    let mut s = format!("{value:.6}");
+    if s.contains('.') {
+        let trimmed = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(trimmed);
+    }
+    if s == "-0" {
+        s.remove(0);
+    }
+    s
+}
+
+/// Escape a string, so it can go in an XML attribute.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        length_unit::LengthUnit,
+        shared::{Angle, PathSegment, Point2d, Point3d},
+    };
+
+    #[test]
+    fn lines_and_arcs() {
+        let mut path = Path::new();
+        path.extend(&PathSegment::Line {
+            end: Point3d {
+                x: LengthUnit(10.0),
+                y: LengthUnit(0.0),
+                z: LengthUnit(0.0),
+            },
+            relative: false,
+        });
+        path.extend(&PathSegment::TangentialArc {
+            radius: LengthUnit(5.0),
+            offset: Angle::half_circle(),
+        });
+        path.close();
+        let style = SvgStyle {
+            fill: Some("#29ffa4".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render([&path], UnitLength::Millimeters, Some(&style)),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="17mm" height="12mm" viewBox="-1 -11 17 12">
+<path d="M 0 0 L 10 0 A 5 5 0 0 1 10 -10 L 0 0 Z" fill="#29ffa4" stroke="black" stroke-width="0.25" stroke-linejoin="round"/>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn scale_and_curves() {
+        let mut circle = Path::new();
+        circle.extend(&PathSegment::Arc {
+            center: Point2d {
+                x: LengthUnit(0.0),
+                y: LengthUnit(0.0),
+            },
+            radius: LengthUnit(1.0),
+            start: Angle::zero(),
+            end: Angle::turn(),
+            relative: false,
+        });
+        let mut involute = Path::new();
+        involute.extend(&PathSegment::CircularInvolute {
+            start_radius: LengthUnit(1.0),
+            end_radius: LengthUnit(2.0),
+            angle: Angle::zero(),
+            reverse: false,
+        });
+        let svg = render([&circle, &involute], UnitLength::Inches, None);
+        // The circle is 2 inches across, plus a millimeter of margin on each side.
+        assert!(svg.contains(r#"width="52.8mm""#), "{svg}");
+        assert!(svg.contains(r#"d="M 1 0 A 1 1 0 0 1 -1 0 A 1 1 0 0 1 1 0 Z""#), "{svg}");
+        // The involute is drawn as a polyline.
+        let involute = svg.lines().nth(2).unwrap();
+        assert!(involute.matches(" L ").count() > 10, "{involute}");
+        assert!(!involute.contains('Z'));
+    }
+}
@@ -1,7 +1,10 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::shared::PostEffectType;
+use crate::{
+    shared::PostEffectType,
+    validate::{self, Validate, ValidationError},
+};
 
 /// Params for starting the engine.
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -65,3 +68,16 @@ impl EngineParams {
         }
     }
 }
+
+impl Validate for EngineParams {
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        validate::multiple_of(&mut errors, "video_res_width", self.video_res_width, 4);
+        validate::multiple_of(&mut errors, "video_res_height", self.video_res_height, 4);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
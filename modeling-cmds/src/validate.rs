@@ -0,0 +1,233 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{length_unit::LengthUnit, Solid3dCutEdges, Solid3dFilletEdge};
+
+/// Checks a value before it's sent to the engine, so that mistakes are caught without a round trip.
+///
+/// Every modeling command implements this. Its checks come from `#[validate(...)]` attributes
+/// on the command's fields, which enforce the constraints in the command's docs.
+/// A command which passes might still be rejected by the engine, e.g. because it refers to an
+/// entity which doesn't exist.
+pub trait Validate {
+    /// Check this value, and return everything wrong with it.
+    fn validate(&self) -> Result<(), Vec<ValidationError>>;
+}
+
+/// Something wrong with one field of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// Path to the field within the value, e.g. `radius`.
+    pub path: String,
+    /// What's wrong with the field.
+    pub problem: Problem,
+}
+
+/// What's wrong with a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// It must be greater than zero.
+    NotPositive {
+        /// The field's value.
+        value: f64,
+    },
+    /// It must not be greater than some maximum.
+    TooLarge {
+        /// The largest value allowed.
+        max: f64,
+        /// The field's value.
+        value: f64,
+    },
+    /// It must not be empty.
+    Empty,
+    /// It must be a multiple of some number.
+    NotMultipleOf {
+        /// What it must be a multiple of.
+        multiple: u32,
+        /// The field's value.
+        value: u32,
+    },
+    /// It must have a certain length.
+    WrongLength {
+        /// The length it should have.
+        expected: usize,
+        /// The field's length.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = &self.path;
+        match &self.problem {
+            Problem::NotPositive { value } => write!(f, "{path} must be positive, but it was {value}"),
+            Problem::TooLarge { max, value } => write!(f, "{path} must be at most {max}, but it was {value}"),
+            Problem::Empty => write!(f, "{path} must not be empty"),
+            Problem::NotMultipleOf { multiple, value } => {
+                write!(f, "{path} must be a multiple of {multiple}, but it was {value}")
+            }
+            Problem::WrongLength { expected, actual } => {
+                write!(f, "{path} must have length {expected}, but it had length {actual}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Numbers which `#[validate(positive)]` and `#[validate(max = ...)]` can check.
+pub(crate) trait Number {
+    fn value(&self) -> f64;
+}
+
+impl Number for f64 {
+    fn value(&self) -> f64 {
+        *self
+    }
+}
+
+impl Number for u32 {
+    fn value(&self) -> f64 {
+        (*self).into()
+    }
+}
+
+impl Number for LengthUnit {
+    fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+/// What `#[validate(positive)]` checks.
+pub(crate) fn positive(errors: &mut Vec<ValidationError>, path: &str, value: &impl Number) {
+    let value = value.value();
+    // NaN isn't positive either.
+    if value <= 0.0 || value.is_nan() {
+        errors.push(error(path, Problem::NotPositive { value }));
+    }
+}
+
+/// What `#[validate(max = ...)]` checks.
+pub(crate) fn max(errors: &mut Vec<ValidationError>, path: &str, value: &impl Number, max: f64) {
+    let value = value.value();
+    if value > max {
+        errors.push(error(path, Problem::TooLarge { max, value }));
+    }
+}
+
+/// What `#[validate(non_empty)]` checks.
+pub(crate) fn non_empty(errors: &mut Vec<ValidationError>, path: &str, len: usize) {
+    if len == 0 {
+        errors.push(error(path, Problem::Empty));
+    }
+}
+
+/// What `#[validate(multiple_of = ...)]` checks.
+pub(crate) fn multiple_of(errors: &mut Vec<ValidationError>, path: &str, value: u32, multiple: u32) {
+    // Only zero is a multiple of zero, and `%` would panic on it.
+    // (`u32::is_multiple_of` handles that, but it's newer than our MSRV.)
+    let is_multiple = value.checked_rem(multiple).map_or(value == 0, |rem| rem == 0);
+    if !is_multiple {
+        errors.push(error(path, Problem::NotMultipleOf { multiple, value }));
+    }
+}
+
+/// Each edge after the first gets one of the extra face IDs, so there's one fewer than there are edges.
+fn extra_face_ids(errors: &mut Vec<ValidationError>, edges: usize, extra_face_ids: &[Uuid]) {
+    let expected = edges.saturating_sub(1);
+    if extra_face_ids.len() != expected {
+        errors.push(error(
+            "extra_face_ids",
+            Problem::WrongLength {
+                expected,
+                actual: extra_face_ids.len(),
+            },
+        ));
+    }
+}
+
+/// Check a fillet has an extra face ID for each edge after the first.
+pub(crate) fn fillet_extra_face_ids(cmd: &Solid3dFilletEdge, errors: &mut Vec<ValidationError>) {
+    let edges = cmd.edge_ids.len() + usize::from(cmd.edge_id.is_some());
+    extra_face_ids(errors, edges, &cmd.extra_face_ids);
+}
+
+/// Check a cut has an extra face ID for each edge after the first.
+pub(crate) fn cut_extra_face_ids(cmd: &Solid3dCutEdges, errors: &mut Vec<ValidationError>) {
+    extra_face_ids(errors, cmd.edge_ids.len(), &cmd.extra_face_ids);
+}
+
+fn error(path: &str, problem: Problem) -> ValidationError {
+    ValidationError {
+        path: path.to_owned(),
+        problem,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{session::EngineParams, BooleanUnion, ModelingCmd, ReconfigureStream};
+
+    #[test]
+    fn fillet() {
+        let fillet = Solid3dFilletEdge::builder()
+            .object_id(Uuid::new_v4())
+            .edge_ids(vec![Uuid::new_v4(), Uuid::new_v4()])
+            .radius(LengthUnit(-1.0))
+            .tolerance(LengthUnit(0.1))
+            .build();
+        let errors = ModelingCmd::Solid3dFilletEdge(fillet).validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                error("radius", Problem::NotPositive { value: -1.0 }),
+                error("extra_face_ids", Problem::WrongLength { expected: 1, actual: 0 }),
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "radius must be positive, but it was -1");
+    }
+
+    #[test]
+    fn boolean_union() {
+        let union = BooleanUnion::builder()
+            .solid_ids(Vec::new())
+            .tolerance(LengthUnit(0.1))
+            .build();
+        assert_eq!(union.validate().unwrap_err(), vec![error("solid_ids", Problem::Empty)]);
+        let union = BooleanUnion::builder()
+            .solid_ids(vec![Uuid::new_v4()])
+            .tolerance(LengthUnit(0.1))
+            .build();
+        assert_eq!(union.validate(), Ok(()));
+    }
+
+    #[test]
+    fn resolution() {
+        let reconfigure = ReconfigureStream::builder().width(1280).height(722).fps(30).build();
+        assert_eq!(
+            reconfigure.validate().unwrap_err(),
+            vec![error(
+                "height",
+                Problem::NotMultipleOf {
+                    multiple: 4,
+                    value: 722
+                }
+            )]
+        );
+        let params = EngineParams {
+            video_res_width: 2,
+            ..Default::default()
+        };
+        assert_eq!(params.validate().unwrap_err().len(), 1);
+        assert_eq!(EngineParams::default().validate(), Ok(()));
+        // Zero is a multiple of everything, and the only multiple of zero.
+        let mut errors = Vec::new();
+        multiple_of(&mut errors, "x", 0, 4);
+        multiple_of(&mut errors, "x", 0, 0);
+        assert!(errors.is_empty());
+        multiple_of(&mut errors, "x", 4, 0);
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -47,6 +47,14 @@ pub enum ErrorCode {
     MessageTypeNotAcceptedForWebRTC,
 }
 
+impl ErrorCode {
+    /// Might the request succeed if it's sent again, unchanged?
+    /// Only true for internal errors, which are the API's fault, not the request's.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::InternalEngine | Self::InternalApi)
+    }
+}
+
 /// Because [`EngineErrorCode`] is a subset of [`ErrorCode`], you can trivially map
 /// each variant of the former to a variant of the latter.
 impl From<EngineErrorCode> for ErrorCode {
@@ -357,6 +365,15 @@ impl From<ExportFile> for RawFile {
     }
 }
 
+impl From<RawFile> for ExportFile {
+    fn from(f: RawFile) -> Self {
+        Self {
+            name: f.name,
+            contents: crate::base64::Base64Data(f.contents),
+        }
+    }
+}
+
 /// An error with an internal message for logging.
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LoggableApiError {
@@ -1,14 +1,10 @@
 //! Use the KittyCAD modeling API to draw a cube and save it to a PNG.
 use std::{env, io::Cursor};
 
-use color_eyre::{
-    eyre::{bail, Context},
-    Result,
-};
+use color_eyre::{eyre::Context, Result};
 use kittycad_modeling_cmds::{
-    id::ModelingCmdId,
+    id::IdGenerator,
     length_unit::LengthUnit,
-    ok_response::OkModelingCmdResponse,
     shared::{PathSegment, Point3d},
     ClosePath, ExtendPath, Extrude, ModelingCmd, MovePathPen, StartPath, TakeSnapshot,
 };
@@ -37,13 +33,29 @@ async fn main() -> Result<()> {
         buffer_reqs: None,
         await_response_timeout: None,
         show_grid: None,
+        heartbeat_interval: None,
+        reconnect: None,
+        post_effect: None,
+        pool: None,
+        replay: None,
+        api_call_id: None,
+        journal: None,
+        webrtc: None,
+        header_auth_token: None,
+        metrics: None,
+        retry: None,
+        auto_batch: None,
+        validate_cmds: None,
     };
-    let mut session = Session::start(session_builder)
+    let session = Session::start(session_builder)
         .await
         .context("could not establish session")?;
 
+    // Generate the same IDs every run, so that runs can be compared.
+    let mut ids = IdGenerator::from_name("cube_png");
+
     // Create a new empty path.
-    let path_id = Uuid::new_v4();
+    let path_id = Uuid::from(ids.next_id());
     let path = path_id.into();
     session
         .run_command(path, ModelingCmd::StartPath(StartPath::default()))
@@ -59,7 +71,10 @@ async fn main() -> Result<()> {
         z: -CUBE_WIDTH,
     };
     session
-        .run_command(random_id(), MovePathPen::builder().path(path).to(start).build().into())
+        .run_command(
+            ids.next_id(),
+            MovePathPen::builder().path(path).to(start).build().into(),
+        )
         .await
         .context("could not move path pen to start")?;
 
@@ -82,10 +97,13 @@ async fn main() -> Result<()> {
         },
         start,
     ];
+    // Send all the segments without waiting for each one's response, then wait for them all.
+    // This saves a round trip to the API per segment.
+    let mut pending = Vec::with_capacity(points.len());
     for point in points {
-        session
-            .run_command(
-                random_id(),
+        let resp = session
+            .send_command(
+                ids.next_id(),
                 ExtendPath::builder()
                     .path(path)
                     .segment(PathSegment::Line {
@@ -96,19 +114,23 @@ async fn main() -> Result<()> {
                     .into(),
             )
             .await
-            .context("could not draw square")?;
+            .context("could not send square segment")?;
+        pending.push(resp);
+    }
+    for resp in pending {
+        resp.await.context("could not draw square")?;
     }
     // Extrude the square into a cube.
     session
         .run_command(
-            random_id(),
+            ids.next_id(),
             ModelingCmd::ClosePath(ClosePath::builder().path_id(path_id).build()),
         )
         .await
         .context("could not close square path")?;
     session
         .run_command(
-            random_id(),
+            ids.next_id(),
             Extrude::builder()
                 // Set required attributes
                 .target(path)
@@ -122,30 +144,22 @@ async fn main() -> Result<()> {
         .await
         .context("could not extrude square into cube")?;
     // Export model as a PNG.
-    let snapshot_resp = session
-        .run_command(
-            random_id(),
+    // Because this uses `run` instead of `run_command`, the response is already
+    // the `TakeSnapshot` output, so there's no need to match on it.
+    let snapshot = session
+        .run(
+            ids.next_id(),
             TakeSnapshot::builder()
                 .format(kittycad_modeling_cmds::ImageFormat::Png)
-                .build()
-                .into(),
+                .build(),
         )
         .await
         .context("could not get PNG snapshot")?;
 
     // Save the PNG to disk.
-    match snapshot_resp {
-        OkModelingCmdResponse::TakeSnapshot(snap) => {
-            let mut img = image::ImageReader::new(Cursor::new(snap.contents));
-            img.set_format(image::ImageFormat::Png);
-            let img = img.decode().context("could not decode PNG bytes")?;
-            img.save(img_output_path).context("could not save PNG to disk")?;
-        }
-        other => bail!("Unexpected response: {other:?}"),
-    };
+    let mut img = image::ImageReader::new(Cursor::new(snapshot.contents));
+    img.set_format(image::ImageFormat::Png);
+    let img = img.decode().context("could not decode PNG bytes")?;
+    img.save(img_output_path).context("could not save PNG to disk")?;
     Ok(())
 }
-
-fn random_id() -> ModelingCmdId {
-    Uuid::new_v4().into()
-}
@@ -6,7 +6,7 @@ use color_eyre::{
     Result,
 };
 use kittycad_modeling_cmds::{
-    id::ModelingCmdId,
+    id::IdGenerator,
     length_unit::LengthUnit,
     ok_response::OkModelingCmdResponse,
     shared::{PathSegment, Point3d},
@@ -38,13 +38,29 @@ async fn main() -> Result<()> {
         buffer_reqs: None,
         await_response_timeout: None,
         show_grid: None,
+        heartbeat_interval: None,
+        reconnect: None,
+        post_effect: None,
+        pool: None,
+        replay: None,
+        api_call_id: None,
+        journal: None,
+        webrtc: None,
+        header_auth_token: None,
+        metrics: None,
+        retry: None,
+        auto_batch: None,
+        validate_cmds: None,
     };
-    let mut session = Session::start(session_builder)
+    let session = Session::start(session_builder)
         .await
         .context("could not establish session")?;
 
+    // Generate the same IDs every run, so that runs can be compared.
+    let mut ids = IdGenerator::from_name("cube_png_batch");
+
     // Create a new empty path.
-    let path_id = Uuid::new_v4();
+    let path_id = Uuid::from(ids.next_id());
     let path = path_id.into();
     session
         .run_command(path, StartPath::default().into())
@@ -60,7 +76,7 @@ async fn main() -> Result<()> {
         z: -CUBE_WIDTH,
     };
     let mut sketch_batch = vec![ModelingCmdReq {
-        cmd_id: random_id(),
+        cmd_id: ids.next_id(),
         cmd: ModelingCmd::MovePathPen(MovePathPen::builder().path(path).to(start).build()),
     }];
 
@@ -85,7 +101,7 @@ async fn main() -> Result<()> {
             start,
         ]
         .map(|end| ModelingCmdReq {
-            cmd_id: random_id(),
+            cmd_id: ids.next_id(),
             cmd: ModelingCmd::ExtendPath(
                 ExtendPath::builder()
                     .path(path)
@@ -96,26 +112,21 @@ async fn main() -> Result<()> {
     );
     sketch_batch.push(ModelingCmdReq {
         cmd: ModelingCmd::ClosePath(ClosePath::builder().path_id(path_id).build()),
-        cmd_id: random_id(),
+        cmd_id: ids.next_id(),
     });
     sketch_batch.push(ModelingCmdReq {
-        cmd: ModelingCmd::Extrude(
-            Extrude::builder()
-                .target(path)
-                .distance(CUBE_WIDTH * 2.0)
-                .build(),
-        ),
-        cmd_id: random_id(),
+        cmd: ModelingCmd::Extrude(Extrude::builder().target(path).distance(CUBE_WIDTH * 2.0).build()),
+        cmd_id: ids.next_id(),
     });
     session
-        .run_batch_no_responses(sketch_batch, random_id())
+        .run_batch_no_responses(sketch_batch, ids.next_id())
         .await
         .context("could not draw cube in batch")?;
 
     // Export model as a PNG.
     let snapshot_resp = session
         .run_command(
-            random_id(),
+            ids.next_id(),
             TakeSnapshot::builder()
                 .format(kittycad_modeling_cmds::ImageFormat::Png)
                 .build()
@@ -136,7 +147,3 @@ async fn main() -> Result<()> {
     };
     Ok(())
 }
-
-fn random_id() -> ModelingCmdId {
-    Uuid::new_v4().into()
-}
@@ -1,140 +1,518 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use futures::{
+    future::BoxFuture,
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
 };
+use kittycad::types::error::Error as ApiError;
 use kittycad_modeling_cmds::{
     id::ModelingCmdId,
-    ok_response::OkModelingCmdResponse,
-    websocket::{ModelingBatch, ModelingCmdReq, OkWebSocketResponseData, WebSocketRequest, WebSocketResponse},
-};
-use reqwest::Upgraded;
-use tokio::{
-    sync::{mpsc, oneshot},
-    time::Instant,
+    websocket::{
+        BatchResponse, ModelingBatch, ModelingCmdReq, OkWebSocketResponseData, WebSocketRequest, WebSocketResponse,
+    },
 };
-use tokio_tungstenite::{tungstenite::Message as WsMsg, WebSocketStream};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message as WsMsg;
 
-use crate::RunCommandError;
+use crate::{health::Health, journal::Recorder, transport::BoxTransport, ReconnectPolicy, RunCommandError};
 
 type Result<T> = std::result::Result<T, RunCommandError>;
 
+/// Receives the response to a request which was sent over the WebSocket.
+pub type ResponseReceiver = oneshot::Receiver<Result<WebSocketResponse>>;
+
+/// Opens a new WebSocket connection to the KittyCAD Modeling API.
+pub type Connect = Box<dyn Fn() -> BoxFuture<'static, std::result::Result<BoxTransport, ApiError>> + Send + Sync>;
+
 #[allow(clippy::large_enum_variant)]
 pub enum Request {
-    SendModelingCmd(ModelingCmdReq, oneshot::Sender<Result<()>>),
-    GetResponse(ModelingCmdId, oneshot::Sender<Result<OkModelingCmdResponse>>),
-    SendModelingBatch(ModelingBatch, oneshot::Sender<Result<()>>),
+    /// Send a modeling command. Once it's sent, the responder gets a receiver for the command's
+    /// response, so the caller can wait for it without blocking other requests.
+    SendModelingCmd(ModelingCmdReq, oneshot::Sender<Result<ResponseReceiver>>),
+    /// Send a batch of modeling commands. Like `SendModelingCmd`, the responder gets a receiver
+    /// for the batch's response, which is keyed by the batch ID.
+    SendModelingBatch(ModelingBatch, oneshot::Sender<Result<ResponseReceiver>>),
+    /// Send a request which the engine doesn't respond to, like new auth headers, or the
+    /// WebRTC handshake's trickle ICE candidates.
+    Send(WebSocketRequest),
 }
 
+/// A request which was sent, and is waiting for its response.
+struct Waiting {
+    responder: oneshot::Sender<Result<WebSocketResponse>>,
+    /// Modeling commands to journal, if the engine acknowledges them.
+    to_journal: Vec<ModelingCmdReq>,
+}
+
+/// Requests which were sent over the WebSocket and are still waiting for their response.
+/// The actor registers each request here before sending it, and the reader task
+/// routes each response back to whoever is waiting for it.
+#[derive(Default)]
+pub struct InFlight {
+    waiting: HashMap<ModelingCmdId, Waiting>,
+    /// Set once the WebSocket closes for good, after which no more responses will arrive.
+    closed: bool,
+    /// Every modeling command the engine has acknowledged which changes the scene, in order.
+    /// Only recorded if the session will replay them after reconnecting.
+    journal: Option<Vec<ModelingCmdReq>>,
+}
+
+impl InFlight {
+    /// Start waiting for the response with this ID.
+    #[allow(clippy::result_large_err)]
+    fn wait_for(&mut self, id: ModelingCmdId, to_journal: Vec<ModelingCmdReq>) -> Result<ResponseReceiver> {
+        if self.closed {
+            return Err(RunCommandError::WebSocketClosed);
+        }
+        let (responder, rx) = oneshot::channel();
+        self.waiting.insert(id, Waiting { responder, to_journal });
+        Ok(rx)
+    }
+
+    /// Send the response to whichever request was waiting for it.
+    fn respond(&mut self, id: ModelingCmdId, resp: WebSocketResponse) {
+        let Some(waiting) = self.waiting.remove(&id) else {
+            return;
+        };
+        if let Some(journal) = &mut self.journal {
+            journal.extend(acknowledged(waiting.to_journal, &resp));
+        }
+        // If the send fails, it's because the caller stopped waiting for this response,
+        // so ignore the error.
+        let _ = waiting.responder.send(Ok(resp));
+    }
+
+    /// The caller stopped waiting for this response, so stop routing it.
+    pub fn cancel(&mut self, id: ModelingCmdId) {
+        match self.waiting.get(&id) {
+            // Keep waiting for responses which should be journaled anyway, so that the journal
+            // still matches the engine's scene. They're removed once the response arrives,
+            // or the connection closes.
+            Some(waiting) if !waiting.to_journal.is_empty() => {}
+            _ => {
+                self.waiting.remove(&id);
+            }
+        }
+    }
+
+    /// None of the outstanding requests will get a response, so fail them all.
+    fn fail_all(&mut self, err: fn() -> RunCommandError) {
+        for (_, waiting) in self.waiting.drain() {
+            let _ = waiting.responder.send(Err(err()));
+        }
+    }
+
+    /// The session is over, so fail all outstanding and future requests.
+    fn close(&mut self, err: fn() -> RunCommandError) {
+        self.closed = true;
+        self.fail_all(err);
+    }
+}
+
+/// How the actor should run its connection.
+pub struct Config {
+    /// Opens a new connection, if the session knows how to.
+    pub connect: Option<Connect>,
+    /// How to reconnect, if the connection drops.
+    pub reconnect: Option<ReconnectPolicy>,
+    /// How long to wait for each response while replaying the journal.
+    pub await_response_timeout: Duration,
+    /// How often to ping the engine, if at all.
+    pub heartbeat_interval: Option<Duration>,
+}
+
+/// State which the session shares with its actor.
+#[derive(Clone)]
+pub struct Shared {
+    pub in_flight: Arc<Mutex<InFlight>>,
+    pub health: Arc<Health>,
+    /// Sends every message which the engine pushed without being asked.
+    pub events: broadcast::Sender<WebSocketResponse>,
+    /// ID of the API call which the current connection is using, once the engine has sent it.
+    pub api_call_id: Arc<Mutex<Option<String>>>,
+    /// API token to authenticate each connection with, if the session sends it in a `Headers`
+    /// request instead of the upgrade request.
+    pub auth_token: Arc<Mutex<Option<String>>>,
+    /// Records every message sent and received, if the session keeps a journal.
+    pub recorder: Recorder,
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Self {
+            in_flight: Default::default(),
+            health: Default::default(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            api_call_id: Default::default(),
+            auth_token: Default::default(),
+            recorder: Default::default(),
+        }
+    }
+}
+
+/// How many events can be buffered for each events stream, before the slowest stream misses some.
+const EVENT_BUFFER: usize = 64;
+
 pub async fn start(
     mut incoming: mpsc::Receiver<Request>,
-    mut write_to_ws: SplitSink<WebSocketStream<Upgraded>, WsMsg>,
-    mut read_from_ws: SplitStream<WebSocketStream<Upgraded>>,
-    timeout: Duration,
+    ws: BoxTransport,
+    Config {
+        connect,
+        reconnect,
+        await_response_timeout,
+        heartbeat_interval,
+    }: Config,
+    shared: Shared,
 ) {
-    let mut responses: HashMap<ModelingCmdId, WebSocketResponse> = HashMap::new();
-    'next_request: while let Some(req) = incoming.recv().await {
-        match req {
-            Request::SendModelingCmd(cmd, responder) => {
-                let ws_msg = WsMsg::Text(
-                    serde_json::to_string(&WebSocketRequest::ModelingCmdReq(cmd))
-                        .expect("ModelingCmdReq can always be serialized"),
-                );
-                let resp = write_to_ws.send(ws_msg).await.map_err(RunCommandError::WebSocketSend);
-                // If the send fails, it's because the caller dropped its end, so ignore the
-                // error because we're done with this request anyway.
-                let _ = responder.send(resp);
-            }
-            Request::GetResponse(cmd_id, responder) => {
-                let start = Instant::now();
-                while start.elapsed() < timeout {
-                    // Check the response map.
-                    // If we've already got the response for this ID, then send it back to the user!
-                    if let Some(resp) = responses.remove(&cmd_id) {
-                        let send_this_to_user = match resp {
-                            WebSocketResponse::Success(s) => {
-                                let resp = s.resp;
-                                match resp {
-                                    OkWebSocketResponseData::Modeling { modeling_response } => Ok(modeling_response),
-                                    _ => {
-                                        // This request ID should be for a modeling request. Something's gone very wrong.
-                                        Err(RunCommandError::ServerSentWrongType)
-                                    }
-                                }
-                            }
-                            WebSocketResponse::Failure(e) => Err(RunCommandError::ModelingApiFailure {
-                                request_id: Some(cmd_id.into()),
-                                errors: e.errors,
-                            }),
-                        };
-                        // If the send fails, it's because the caller dropped its end, so ignore the
-                        // error because we're done with this request anyway.
-                        let _ = responder.send(send_this_to_user);
-                        // Finished this request! Actor is ready for the next request.
-                        continue 'next_request;
+    let in_flight = shared.in_flight.clone();
+    in_flight.lock().unwrap().journal = reconnect
+        .as_ref()
+        .filter(|policy| policy.replay_journal)
+        .map(|_| Vec::new());
+    let health = shared.health.clone();
+    let recorder = shared.recorder.clone();
+    let auth_token = shared.auth_token.clone();
+    // The reader can't write to the WebSocket, so it sends any replies to the engine's requests
+    // back to the actor.
+    let (replies_tx, mut replies) = mpsc::unbounded_channel();
+    let reader = Reader {
+        in_flight: in_flight.clone(),
+        shared,
+        replies: replies_tx,
+    };
+    let (mut write_to_ws, read_from_ws) = ws.split();
+    // If the connection fails here, the reader will notice.
+    let _ = authenticate(&mut write_to_ws, &recorder, &auth_token).await;
+    let mut reader_task = tokio::task::spawn(reader.clone().route_responses(read_from_ws));
+    let mut connected = true;
+    let mut heartbeat = heartbeat_interval.map(|period| {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    });
+    loop {
+        tokio::select! {
+            req = incoming.recv() => {
+                // No more requests means the session was dropped, so we're done.
+                let Some(req) = req else {
+                    break;
+                };
+                let (id, req, responder) = match req {
+                    Request::SendModelingCmd(cmd, responder) => {
+                        (cmd.cmd_id, WebSocketRequest::ModelingCmdReq(cmd), responder)
                     }
-                    // If not, get a response from the WebSocket.
-                    // If we can't get any response, the WebSocket must have been closed.
-                    let Some(msg) = read_from_ws.next().await else {
-                        // If the send fails, it's because the caller dropped its end, so ignore
-                        // the error because we're done with this request anyway.
-                        let _ = responder.send(Err(RunCommandError::WebSocketClosed));
-                        // Probably no point getting another request, but the user may try to,
-                        // so we should respect them.
-                        continue 'next_request;
-                    };
-                    // Couldn't read from WebSocket? Try again.
-                    let Ok(msg) = msg else {
-                        continue;
-                    };
-                    // WebSocket response wasn't text? Try again.
-                    let Some(resp_text) = text_from_ws(msg) else {
-                        continue;
-                    };
-                    // Couldn't decode the response? Try again.
-                    let Ok(resp) = decode_websocket_text(&resp_text) else {
-                        continue;
-                    };
-                    if let Some(id) = resp.request_id() {
-                        responses.insert(id.into(), resp);
-                    } else {
+                    Request::SendModelingBatch(batch, responder) => {
+                        (batch.batch_id, WebSocketRequest::ModelingCmdBatchReq(batch), responder)
+                    }
+                    Request::Send(req) => {
+                        // If the request can't be sent, the connection is closing, and the reader will notice.
+                        let _ = send(&mut write_to_ws, &recorder, &req).await;
                         continue;
                     }
-                }
-                // If the send fails, it's because the caller dropped its end, so cancel this request
-                // and wait for the next request.
-                if responder.send(Err(RunCommandError::TimeOutWaitingForResponse)).is_err() {
-                    continue 'next_request;
-                }
-            }
-            Request::SendModelingBatch(batch, responder) => {
-                let ws_msg = WsMsg::Text(
-                    serde_json::to_string(&WebSocketRequest::ModelingCmdBatchReq(batch))
-                        .expect("ModelingCmdReq can always be serialized"),
-                );
-                let resp = write_to_ws.send(ws_msg).await.map_err(RunCommandError::WebSocketSend);
+                };
+                let resp = send_and_wait_for(&mut write_to_ws, &recorder, &in_flight, id, &req).await;
                 // If the send fails, it's because the caller dropped its end, so ignore the
                 // error because we're done with this request anyway.
                 let _ = responder.send(resp);
             }
+            Some(reply) = replies.recv() => {
+                // If the reply can't be sent, the connection is closing, and the reader will notice.
+                let _ = send(&mut write_to_ws, &recorder, &reply).await;
+            }
+            _ = tick(&mut heartbeat), if connected => {
+                health.ping();
+                // If the ping can't be sent, the connection is closing, and the reader will notice.
+                let _ = send(&mut write_to_ws, &recorder, &WebSocketRequest::Ping {}).await;
+            }
+            // The reader only finishes once the WebSocket has closed.
+            _ = &mut reader_task, if connected => {
+                let new_ws = match (&connect, &reconnect) {
+                    (Some(connect), Some(policy)) => reconnect_with(connect, policy).await,
+                    _ => None,
+                };
+                let Some(new_ws) = new_ws else {
+                    in_flight.lock().unwrap().close(|| RunCommandError::WebSocketClosed);
+                    health.close();
+                    connected = false;
+                    continue;
+                };
+                health.reset();
+                *reader.shared.api_call_id.lock().unwrap() = None;
+                // Requests sent over the old connection won't get a response over the new one.
+                in_flight.lock().unwrap().fail_all(|| RunCommandError::Reconnected);
+                let read_from_ws;
+                (write_to_ws, read_from_ws) = new_ws.split();
+                reader_task = tokio::task::spawn(reader.clone().route_responses(read_from_ws));
+                // The engine won't run any commands, including the replayed ones, until the new
+                // connection is authenticated.
+                let _ = authenticate(&mut write_to_ws, &recorder, &auth_token).await;
+                if replay_journal(&mut write_to_ws, &recorder, &in_flight, await_response_timeout).await.is_err() {
+                    // The new engine's scene doesn't match the old one, so later commands which
+                    // reference the old scene would fail in confusing ways. End the session instead.
+                    in_flight.lock().unwrap().close(|| RunCommandError::ReplayFailed);
+                    health.close();
+                }
+            }
+        }
+    }
+    // The session was dropped, so nobody is waiting for any more responses.
+    reader_task.abort();
+}
+
+/// Wait for the next heartbeat. If there's no heartbeat, wait forever.
+async fn tick(heartbeat: &mut Option<tokio::time::Interval>) {
+    match heartbeat {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Try to open a new connection, backing off before each attempt.
+async fn reconnect_with(connect: &Connect, policy: &ReconnectPolicy) -> Option<BoxTransport> {
+    for attempt in 0..policy.max_attempts {
+        tokio::time::sleep(policy.backoff(attempt)).await;
+        if let Ok(ws) = connect().await {
+            return Some(ws);
+        }
+    }
+    None
+}
+
+/// Send the session's auth token over a new connection, if it authenticates that way.
+async fn authenticate(
+    write_to_ws: &mut SplitSink<BoxTransport, WsMsg>,
+    recorder: &Recorder,
+    auth_token: &Mutex<Option<String>>,
+) -> Result<()> {
+    let Some(token) = auth_token.lock().unwrap().clone() else {
+        return Ok(());
+    };
+    send(write_to_ws, recorder, &auth_headers(&token)).await
+}
+
+/// A request which authenticates the connection with this API token.
+pub fn auth_headers(token: &str) -> WebSocketRequest {
+    WebSocketRequest::Headers {
+        headers: HashMap::from([("Authorization".to_owned(), format!("Bearer {token}"))]),
+    }
+}
+
+/// Re-send every journaled command over a new connection, so that the new engine's scene
+/// matches the old one. Waits until the engine has acknowledged all of them.
+async fn replay_journal(
+    write_to_ws: &mut SplitSink<BoxTransport, WsMsg>,
+    recorder: &Recorder,
+    in_flight: &Mutex<InFlight>,
+    await_response_timeout: Duration,
+) -> Result<()> {
+    let journal = in_flight.lock().unwrap().journal.clone().unwrap_or_default();
+    let mut pending = Vec::with_capacity(journal.len());
+    for cmd in journal {
+        // These commands are already in the journal, so don't journal them again.
+        let rx = in_flight.lock().unwrap().wait_for(cmd.cmd_id, Vec::new())?;
+        send(write_to_ws, recorder, &WebSocketRequest::ModelingCmdReq(cmd)).await?;
+        pending.push(rx);
+    }
+    for rx in pending {
+        let resp = tokio::time::timeout(await_response_timeout, rx)
+            .await
+            .map_err(|_| RunCommandError::TimeOutWaitingForResponse)?
+            .map_err(|_| RunCommandError::ActorFailed)??;
+        if let WebSocketResponse::Failure(e) = resp {
+            return Err(RunCommandError::modeling_api_failure(e.request_id, e.errors));
+        }
+    }
+    Ok(())
+}
+
+/// Reads responses from the WebSocket, and sends each one wherever it should go.
+#[derive(Clone)]
+struct Reader {
+    in_flight: Arc<Mutex<InFlight>>,
+    shared: Shared,
+    /// Replies to requests from the engine, for the actor to send.
+    replies: mpsc::UnboundedSender<WebSocketRequest>,
+}
+
+impl Reader {
+    /// Read every response from the WebSocket, and send it to whichever request is waiting for it.
+    async fn route_responses(self, mut read_from_ws: SplitStream<BoxTransport>) {
+        while let Some(msg) = read_from_ws.next().await {
+            // Couldn't read from WebSocket? Try again.
+            let Ok(msg) = msg else {
+                continue;
+            };
+            // Couldn't decode the response? Try again.
+            let Some(resp) = decode_websocket_msg(msg) else {
+                continue;
+            };
+            self.shared.recorder.received(&resp);
+            match resp.request_id() {
+                Some(id) => self.in_flight.lock().unwrap().respond(id.into(), resp),
+                // Responses without an ID aren't for any particular request.
+                None => self.handle_event(resp),
+            }
         }
     }
+
+    /// Handle a message which the engine sent without being asked.
+    fn handle_event(&self, resp: WebSocketResponse) {
+        if let WebSocketResponse::Success(s) = &resp {
+            match &s.resp {
+                // Pongs answer the actor's heartbeat, so they're not interesting to anyone else.
+                OkWebSocketResponseData::Pong {} => {
+                    self.shared.health.pong();
+                    return;
+                }
+                OkWebSocketResponseData::MetricsRequest {} => {
+                    // The engine only uses these metrics to tune the video stream,
+                    // so there's nothing worth reporting.
+                    let _ = self.replies.send(WebSocketRequest::MetricsResponse {
+                        metrics: Box::default(),
+                    });
+                }
+                OkWebSocketResponseData::ModelingSessionData { session } => {
+                    *self.shared.api_call_id.lock().unwrap() = Some(session.api_call_id.clone());
+                }
+                _ => {}
+            }
+        }
+        // If the send fails, nobody is listening for events, which is fine.
+        let _ = self.shared.events.send(resp);
+    }
+}
+
+/// Send a request over the WebSocket, and start waiting for the response with the given ID.
+async fn send_and_wait_for(
+    write_to_ws: &mut SplitSink<BoxTransport, WsMsg>,
+    recorder: &Recorder,
+    in_flight: &Mutex<InFlight>,
+    id: ModelingCmdId,
+    req: &WebSocketRequest,
+) -> Result<ResponseReceiver> {
+    // Register the request before sending it, so that the reader task knows where to
+    // route the response, even if it arrives immediately.
+    let rx = {
+        let mut in_flight = in_flight.lock().unwrap();
+        let to_journal = if in_flight.journal.is_some() {
+            replayable_cmds(req)
+        } else {
+            Vec::new()
+        };
+        in_flight.wait_for(id, to_journal)?
+    };
+    if let Err(e) = send(write_to_ws, recorder, req).await {
+        // The request was never sent, so there's no response to wait for.
+        in_flight.lock().unwrap().waiting.remove(&id);
+        return Err(e);
+    }
+    Ok(rx)
+}
+
+/// Send a request over the WebSocket, as JSON text.
+async fn send(
+    write_to_ws: &mut SplitSink<BoxTransport, WsMsg>,
+    recorder: &Recorder,
+    req: &WebSocketRequest,
+) -> Result<()> {
+    recorder.sent(req);
+    let ws_msg = WsMsg::Text(serde_json::to_string(req).expect("WebSocketRequest can always be serialized"));
+    write_to_ws.send(ws_msg).await.map_err(RunCommandError::WebSocketSend)
+}
+
+/// The modeling commands in this request which are worth replaying: the ones which change the
+/// scene, or how it's displayed (e.g. hiding objects, or their colors). Queries, exports and
+/// camera moves would just make the journal longer.
+fn replayable_cmds(req: &WebSocketRequest) -> Vec<ModelingCmdReq> {
+    let cmds = match req {
+        WebSocketRequest::ModelingCmdReq(cmd) => std::slice::from_ref(cmd),
+        WebSocketRequest::ModelingCmdBatchReq(batch) => batch.requests.as_slice(),
+        _ => &[],
+    };
+    cmds.iter()
+        .filter(|req| !req.cmd.is_query() && !req.cmd.affects_camera_only())
+        .cloned()
+        .collect()
 }
 
-/// Given the text from a WebSocket, deserialize its JSON.
-/// Returns OK if the WebSocket's JSON represents a successful response.
-/// Returns an error if the WebSocket's JSON represented a failure response.
-#[allow(clippy::result_large_err)]
-fn decode_websocket_text(text: &str) -> Result<WebSocketResponse> {
-    let resp = serde_json::from_str(text)?;
-    Ok(resp)
+/// Which of these modeling commands did the engine acknowledge in this response?
+fn acknowledged(cmds: Vec<ModelingCmdReq>, resp: &WebSocketResponse) -> Vec<ModelingCmdReq> {
+    let WebSocketResponse::Success(s) = resp else {
+        return Vec::new();
+    };
+    match &s.resp {
+        // Only some commands in a batch might have succeeded.
+        OkWebSocketResponseData::ModelingBatch { responses } => cmds
+            .into_iter()
+            .filter(|cmd| matches!(responses.get(&cmd.cmd_id), Some(BatchResponse::Success { .. })))
+            .collect(),
+        _ => cmds,
+    }
 }
 
-/// Find the text in a WebSocket message, if there's any.
-fn text_from_ws(msg: WsMsg) -> Option<String> {
+/// Decode the response in a WebSocket message, if there's any.
+/// Text messages carry JSON. Binary messages carry BSON, which the engine uses for exported files.
+fn decode_websocket_msg(msg: WsMsg) -> Option<WebSocketResponse> {
     match msg {
-        WsMsg::Text(text) => Some(text),
+        WsMsg::Text(text) => serde_json::from_str(&text).ok(),
+        WsMsg::Binary(bytes) => bson::from_slice(&bytes).ok(),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use kittycad_modeling_cmds::{
+        ok_response::OkModelingCmdResponse, websocket::SuccessWebSocketResponse, ModelingCmd, StartPath,
+    };
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn cmd() -> ModelingCmdReq {
+        ModelingCmdReq {
+            cmd: ModelingCmd::StartPath(StartPath::default()),
+            cmd_id: Uuid::new_v4().into(),
+        }
+    }
+
+    #[test]
+    fn cancel_stops_waiting() {
+        let mut in_flight = InFlight::default();
+        let id = cmd().cmd_id;
+        let _rx = in_flight.wait_for(id, Vec::new()).unwrap();
+        in_flight.cancel(id);
+        assert!(in_flight.waiting.is_empty());
+    }
+
+    #[test]
+    fn cancel_still_journals() {
+        let mut in_flight = InFlight {
+            journal: Some(Vec::new()),
+            ..Default::default()
+        };
+        let cmd = cmd();
+        let id = cmd.cmd_id;
+        let _rx = in_flight.wait_for(id, vec![cmd]).unwrap();
+        in_flight.cancel(id);
+        // Nobody's waiting for the response any more, but the engine still ran the command,
+        // so it should still be journaled.
+        let resp = WebSocketResponse::Success(SuccessWebSocketResponse {
+            success: true,
+            request_id: Some(id.into()),
+            resp: OkWebSocketResponseData::Modeling {
+                modeling_response: OkModelingCmdResponse::Empty,
+            },
+        });
+        in_flight.respond(id, resp);
+        assert!(in_flight.waiting.is_empty());
+        assert_eq!(in_flight.journal.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use kittycad_modeling_cmds::{
+    id::ModelingCmdId,
+    websocket::{
+        BatchResponse, FailureWebSocketResponse, ModelingBatch, ModelingCmdReq, OkWebSocketResponseData,
+        SuccessWebSocketResponse, WebSocketResponse,
+    },
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use uuid::Uuid;
+
+use crate::{
+    actor::{Request, ResponseReceiver, Shared},
+    pending::AwaitResponse,
+    timeout, RunCommandError,
+};
+
+type Result<T> = std::result::Result<T, RunCommandError>;
+
+/// How a session should automatically batch commands.
+///
+/// Consecutive commands which are [safe to batch](kittycad_modeling_cmds::ModelingCmd::is_safe_to_batch)
+/// are held back, and sent together as one batch once any other request is sent, the batch is
+/// full, or the first command has waited long enough. Each command still gets its own
+/// [`PendingResponse`](crate::PendingResponse), so callers don't need to know about batches.
+/// This saves a lot of WebSocket messages when sending many small commands, like the segments
+/// of a long path.
+#[derive(Debug, Clone)]
+pub struct BatchPolicy {
+    /// Send the batch once it has this many commands.
+    pub max_cmds: usize,
+    /// Send the batch once its first command has waited this long, even if no other request
+    /// came along. This is added to the latency of every batched command.
+    pub max_delay: Duration,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            max_cmds: 100,
+            max_delay: Duration::from_millis(5),
+        }
+    }
+}
+
+/// A command which is waiting to be sent in the next batch.
+struct Batched {
+    req: ModelingCmdReq,
+    responder: oneshot::Sender<Result<WebSocketResponse>>,
+}
+
+/// Start batching the session's requests before they reach the actor.
+/// Returns where to send requests to instead of the actor.
+pub(crate) fn spawn(
+    actor_tx: mpsc::Sender<Request>,
+    policy: BatchPolicy,
+    shared: Shared,
+    await_response_timeout: Duration,
+) -> mpsc::Sender<Request> {
+    let (tx, rx) = mpsc::channel(actor_tx.max_capacity());
+    let batcher = Batcher {
+        actor_tx,
+        shared,
+        await_response_timeout,
+        batch: Vec::new(),
+    };
+    tokio::task::spawn(batcher.run(rx, policy));
+    tx
+}
+
+struct Batcher {
+    actor_tx: mpsc::Sender<Request>,
+    shared: Shared,
+    await_response_timeout: Duration,
+    /// Commands to send in the next batch, in the order they were sent.
+    batch: Vec<Batched>,
+}
+
+impl Batcher {
+    async fn run(mut self, mut rx: mpsc::Receiver<Request>, policy: BatchPolicy) {
+        let deadline = tokio::time::sleep(Duration::ZERO);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                req = rx.recv() => {
+                    let Some(req) = req else {
+                        // The session was dropped, but it might still be waiting for the batched commands.
+                        break;
+                    };
+                    match req {
+                        Request::SendModelingCmd(req, responder) if req.cmd.is_safe_to_batch() => {
+                            // Answer straight away, so the caller can send its next command,
+                            // which might join this batch too.
+                            let (tx, rx) = oneshot::channel();
+                            let _ = responder.send(Ok(rx));
+                            if self.batch.is_empty() {
+                                deadline.as_mut().reset(Instant::now() + policy.max_delay);
+                            }
+                            self.batch.push(Batched { req, responder: tx });
+                            if self.batch.len() >= policy.max_cmds {
+                                self.flush().await;
+                            }
+                        }
+                        // Anything else has to wait until the engine has the batched commands,
+                        // so that the engine gets everything in order.
+                        req => {
+                            self.flush().await;
+                            if self.actor_tx.send(req).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                () = &mut deadline, if !self.batch.is_empty() => self.flush().await,
+            }
+        }
+        self.flush().await;
+    }
+
+    /// Send every batched command, and route the batch's response back to each command.
+    async fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let (requests, responders): (Vec<_>, Vec<_>) = self
+            .batch
+            .drain(..)
+            .map(|Batched { req, responder }| (req, responder))
+            .unzip();
+        let cmd_ids: Vec<_> = requests.iter().map(|req| req.cmd_id).collect();
+        // The engine only responds once it's run every command in the batch.
+        let timeout = requests
+            .iter()
+            .map(|req| timeout::timeout_for(&req.cmd, self.await_response_timeout))
+            .max()
+            .unwrap_or(self.await_response_timeout);
+        let batch_id: ModelingCmdId = Uuid::new_v4().into();
+        let batch = ModelingBatch {
+            requests,
+            batch_id,
+            responses: true,
+        };
+        let (tx, rx) = oneshot::channel();
+        let sent = match self.actor_tx.send(Request::SendModelingBatch(batch, tx)).await {
+            Ok(()) => rx.await.unwrap_or(Err(RunCommandError::ActorFailed)),
+            Err(_) => Err(RunCommandError::ActorFailed),
+        };
+        let responders = cmd_ids.into_iter().zip(responders).collect();
+        // Don't hold up the next batch while waiting for this one's response.
+        tokio::task::spawn(respond(batch_id, sent, timeout, self.shared.clone(), responders));
+    }
+}
+
+/// Wait for the batch's response, and split it into each command's response.
+async fn respond(
+    batch_id: ModelingCmdId,
+    sent: Result<ResponseReceiver>,
+    timeout: Duration,
+    shared: Shared,
+    responders: Vec<(ModelingCmdId, oneshot::Sender<Result<WebSocketResponse>>)>,
+) {
+    let resp = match sent {
+        Ok(rx) => AwaitResponse::new(batch_id, rx, timeout, shared).await,
+        Err(e) => Err(e),
+    };
+    for (cmd_id, responder) in responders {
+        let resp = match &resp {
+            Ok(WebSocketResponse::Success(SuccessWebSocketResponse {
+                resp: OkWebSocketResponseData::ModelingBatch { responses },
+                ..
+            })) => Ok(match responses.get(&cmd_id) {
+                Some(BatchResponse::Success { response }) => WebSocketResponse::Success(SuccessWebSocketResponse {
+                    success: true,
+                    request_id: Some(cmd_id.into()),
+                    resp: OkWebSocketResponseData::Modeling {
+                        modeling_response: response.clone(),
+                    },
+                }),
+                Some(BatchResponse::Failure { errors }) => failure(cmd_id, errors.clone()),
+                // The engine didn't try this command, because an earlier one in the batch failed.
+                None => failure(cmd_id, Vec::new()),
+            }),
+            // This request ID should be for a batch. Something's gone very wrong.
+            Ok(WebSocketResponse::Success(_)) => Err(RunCommandError::ServerSentWrongType),
+            // The whole batch failed, so each command did.
+            Ok(WebSocketResponse::Failure(e)) => Ok(failure(cmd_id, e.errors.clone())),
+            Err(e) => Err(copy_err(e)),
+        };
+        // If the send fails, it's because the caller stopped waiting for this response.
+        let _ = responder.send(resp);
+    }
+}
+
+fn failure(cmd_id: ModelingCmdId, errors: Vec<kittycad_modeling_cmds::websocket::ApiError>) -> WebSocketResponse {
+    WebSocketResponse::Failure(FailureWebSocketResponse {
+        success: false,
+        request_id: Some(cmd_id.into()),
+        errors,
+    })
+}
+
+/// Every command in a batch fails with the same error.
+/// Errors aren't `Clone`, but waiting for a response only fails with errors that have no
+/// details, so they can be copied.
+fn copy_err(e: &RunCommandError) -> RunCommandError {
+    match e {
+        RunCommandError::WebSocketClosed => RunCommandError::WebSocketClosed,
+        RunCommandError::TimeOutWaitingForResponse => RunCommandError::TimeOutWaitingForResponse,
+        RunCommandError::EngineUnresponsive => RunCommandError::EngineUnresponsive,
+        RunCommandError::Reconnected => RunCommandError::Reconnected,
+        RunCommandError::ReplayFailed => RunCommandError::ReplayFailed,
+        _ => RunCommandError::ActorFailed,
+    }
+}
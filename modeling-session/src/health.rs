@@ -0,0 +1,80 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Tracks whether the engine is still answering the session's pings.
+/// Shared between a session and its actor.
+#[derive(Default)]
+pub(crate) struct Health {
+    inner: Mutex<HealthState>,
+}
+
+#[derive(Default)]
+struct HealthState {
+    /// When the oldest unanswered ping was sent.
+    ping_sent: Option<Instant>,
+    /// Round-trip time of the most recently answered ping.
+    latency: Option<Duration>,
+    /// Set when a ping goes unanswered for a whole heartbeat interval, or the connection closes.
+    unhealthy: bool,
+}
+
+impl Health {
+    /// A ping is about to be sent.
+    /// If the previous ping still hasn't been answered, the engine isn't responding.
+    pub(crate) fn ping(&self) {
+        let mut state = self.inner.lock().unwrap();
+        if state.ping_sent.is_some() {
+            state.unhealthy = true;
+        } else {
+            state.ping_sent = Some(Instant::now());
+        }
+    }
+
+    /// The engine answered a ping.
+    pub(crate) fn pong(&self) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(sent) = state.ping_sent.take() {
+            state.latency = Some(sent.elapsed());
+        }
+        state.unhealthy = false;
+    }
+
+    /// The session has a new connection, so forget about the old one.
+    pub(crate) fn reset(&self) {
+        *self.inner.lock().unwrap() = HealthState::default();
+    }
+
+    /// The connection closed for good.
+    pub(crate) fn close(&self) {
+        self.inner.lock().unwrap().unhealthy = true;
+    }
+
+    pub(crate) fn latency(&self) -> Option<Duration> {
+        self.inner.lock().unwrap().latency
+    }
+
+    pub(crate) fn is_healthy(&self) -> bool {
+        !self.inner.lock().unwrap().unhealthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unanswered_ping_is_unhealthy() {
+        let health = Health::default();
+        health.ping();
+        assert!(health.is_healthy());
+        // The next heartbeat comes round before the engine answers.
+        health.ping();
+        assert!(!health.is_healthy());
+        // Late is better than never.
+        health.pong();
+        assert!(health.is_healthy());
+        assert!(health.latency().is_some());
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use kittycad_modeling_cmds::websocket::{WebSocketRequest, WebSocketResponse};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncWriteExt, BufWriter},
+    sync::mpsc,
+};
+
+/// One message in a session's journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// When the message was sent or received, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// The message itself.
+    #[serde(flatten)]
+    pub message: JournalMessage,
+}
+
+/// A message which the session sent to, or received from, the engine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "direction", content = "message", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum JournalMessage {
+    /// The session sent this request to the engine.
+    Sent(WebSocketRequest),
+    /// The session received this response from the engine.
+    Received(WebSocketResponse),
+}
+
+/// Every message a session sent to or received from the engine, in order.
+/// Record one by setting [`SessionBuilder::journal`](crate::SessionBuilder::journal),
+/// and replay it with [`Session::replay`](crate::Session::replay).
+///
+/// Journals are stored as newline-delimited JSON, with one [`JournalEntry`] per line.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    /// Each message, in the order it was sent or received.
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Read a journal from a file.
+    pub async fn read(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let text = tokio::fs::read_to_string(path).await?;
+        Self::parse(&text)
+    }
+
+    /// Parse a journal from newline-delimited JSON.
+    pub fn parse(text: &str) -> std::io::Result<Self> {
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("invalid journal entry on line {}: {e}", i + 1),
+                    )
+                })
+            })
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self { entries })
+    }
+
+    /// The response which was received for this request ID, if any.
+    pub(crate) fn response_to(&self, request_id: uuid::Uuid) -> Option<&WebSocketResponse> {
+        self.entries.iter().find_map(|entry| match &entry.message {
+            JournalMessage::Received(resp) if resp.request_id() == Some(request_id) => Some(resp),
+            _ => None,
+        })
+    }
+}
+
+/// Appends messages to a session's journal file, if it has one.
+/// Writing happens in a background task, so that recording never blocks the session.
+#[derive(Clone, Default)]
+pub(crate) struct Recorder {
+    lines: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl Recorder {
+    /// Start recording into this file, replacing anything already in it.
+    pub(crate) async fn create(path: &Path) -> std::io::Result<Self> {
+        let file = tokio::fs::File::create(path).await?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        tokio::task::spawn(async move {
+            let mut file = BufWriter::new(file);
+            while let Some(line) = rx.recv().await {
+                // Flush every line, so the journal is still useful if the process crashes,
+                // which is often exactly when you need it.
+                let written = async {
+                    file.write_all(line.as_bytes()).await?;
+                    file.write_all(b"\n").await?;
+                    file.flush().await
+                };
+                // The journal is only for debugging, so a failed write shouldn't break the session.
+                if written.await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { lines: Some(tx) })
+    }
+
+    /// Record a request which was sent to the engine.
+    /// Header values are redacted, because they're usually credentials.
+    pub(crate) fn sent(&self, req: &WebSocketRequest) {
+        self.record(|| match req {
+            WebSocketRequest::Headers { headers } => JournalMessage::Sent(WebSocketRequest::Headers {
+                headers: headers
+                    .keys()
+                    .map(|name| (name.clone(), "<redacted>".to_owned()))
+                    .collect(),
+            }),
+            req => JournalMessage::Sent(req.clone()),
+        });
+    }
+
+    /// Record a response which was received from the engine.
+    pub(crate) fn received(&self, resp: &WebSocketResponse) {
+        self.record(|| JournalMessage::Received(resp.clone()));
+    }
+
+    fn record(&self, message: impl FnOnce() -> JournalMessage) {
+        let Some(lines) = &self.lines else {
+            return;
+        };
+        let entry = JournalEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            message: message(),
+        };
+        let line = serde_json::to_string(&entry).expect("JournalEntry can always be serialized");
+        // If the send fails, the journal couldn't be written to, so just stop recording.
+        let _ = lines.send(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reports_bad_line() {
+        let text = "{\"timestamp_ms\":1,\"direction\":\"sent\",\"message\":{\"type\":\"ping\"}}\n\nnot json\n";
+        let err = Journal::parse(text).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{err}");
+    }
+
+    #[test]
+    fn parse_entries() {
+        let text = "{\"timestamp_ms\":1,\"direction\":\"sent\",\"message\":{\"type\":\"ping\"}}\n";
+        let journal = Journal::parse(text).unwrap();
+        assert!(matches!(
+            journal.entries[..],
+            [JournalEntry {
+                timestamp_ms: 1,
+                message: JournalMessage::Sent(WebSocketRequest::Ping {})
+            }]
+        ));
+    }
+}
@@ -1,21 +1,58 @@
 //! Establish a modeling session with the KittyCAD API.
 
-use std::time::Duration;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use futures::StreamExt;
+use futures::{stream::BoxStream, FutureExt, StreamExt};
 use kittycad::{types::error::Error as ApiError, Client};
 use kittycad_modeling_cmds::{
+    format::OutputFormat3d,
     id::ModelingCmdId,
     ok_response::OkModelingCmdResponse,
-    websocket::{ModelingBatch, ModelingCmdReq},
-    ModelingCmd,
+    session::EngineParams,
+    shared::PostEffectType,
+    validate::{Validate, ValidationError},
+    websocket::{
+        BatchResponse, ErrorCode, ModelingBatch, ModelingCmdReq, OkWebSocketResponseData, RawFile, WebSocketRequest,
+        WebSocketResponse,
+    },
+    Export, ModelingCmd, ModelingCmdVariant,
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::Instrument;
 use uuid::Uuid;
 
+pub use self::batcher::BatchPolicy;
+pub use self::journal::{Journal, JournalEntry, JournalMessage};
+pub use self::metrics::{CommandOutcome, Metrics};
+pub use self::pending::PendingResponse;
+pub use self::pool::{PoolMetrics, SessionLease, SessionPool};
+pub use self::reconnect::ReconnectPolicy;
+pub use self::retry::RetryPolicy;
+pub use self::transport::Transport;
+#[cfg(feature = "webrtc")]
+pub use self::video::VideoFrame;
+use self::{journal::Recorder, metrics::CommandTelemetry, pending::AwaitResponse, transport::BoxTransport};
+
 mod actor;
+mod batcher;
+mod health;
+mod journal;
+mod metrics;
+mod pending;
+mod pool;
+mod reconnect;
+mod retry;
+mod timeout;
+mod transport;
+#[cfg(feature = "webrtc")]
+mod video;
 
 /// Parameters for starting a session with the KittyCAD Modeling API.
+#[derive(Clone)]
 pub struct SessionBuilder {
     /// Client to the KittyCAD API.
     pub client: Client,
@@ -30,22 +67,166 @@ pub struct SessionBuilder {
     /// How many requests for sending/receiving to/from the API can be in-flight at once.
     pub buffer_reqs: Option<usize>,
     /// How long to wait for the response to a modeling command.
-    /// Defaults to 10 seconds.
+    /// Defaults to 10 seconds. Commands which are known to be slow, like imports, exports and
+    /// boolean operations, get at least a minute, or longer.
     pub await_response_timeout: Option<Duration>,
     /// Show the grid?
     pub show_grid: Option<bool>,
+    /// How often to ping the engine, to keep the connection alive and check it's still responding.
+    /// If None, the session never pings the engine.
+    pub heartbeat_interval: Option<Duration>,
+    /// How to reconnect if the WebSocket connection drops.
+    /// If None, the session ends when the connection drops.
+    pub reconnect: Option<ReconnectPolicy>,
+    /// Post-processing effect for the engine to render with, e.g. SSAO.
+    pub post_effect: Option<PostEffectType>,
+    /// Which pool of engine instances to use.
+    /// If None, the default pool is used.
+    pub pool: Option<String>,
+    /// If given, when the session ends, the engine writes the modeling commands sent during the
+    /// session out to this filename. For debugging.
+    pub replay: Option<String>,
+    /// API call ID, for distributed tracing.
+    pub api_call_id: Option<String>,
+    /// If given, the session records every message it sends to and receives from the engine
+    /// into a [`Journal`] at this path, replacing whatever was there.
+    /// Unlike [`SessionBuilder::replay`], this is recorded by the client, so it works with any engine.
+    /// Sessions shouldn't share a journal, so don't set this on a [`SessionPool`]'s builder.
+    pub journal: Option<PathBuf>,
+    /// Stream video from the engine over WebRTC? See [`Session::video_frames`].
+    /// Needs the `webrtc` feature. Defaults to false.
+    pub webrtc: Option<bool>,
+    /// If given, the session authenticates each connection by sending this API token in a
+    /// [`WebSocketRequest::Headers`] request as soon as it opens, instead of relying on the
+    /// client's token in the HTTP upgrade request. Some proxies can't forward the upgrade
+    /// request's headers, so use this when connecting through one.
+    /// The client's own token isn't needed then, so it can be empty.
+    /// Use [`Session::refresh_token`] to replace the token before it expires.
+    pub header_auth_token: Option<String>,
+    /// Reports metrics about every command the session runs.
+    /// Every command is also traced with a `tracing` span, whether this is set or not.
+    pub metrics: Option<Arc<dyn Metrics>>,
+    /// How to retry read-only commands which failed because of a temporary problem in the engine.
+    /// If None, commands are never retried.
+    pub retry: Option<RetryPolicy>,
+    /// If given, the session automatically sends consecutive commands which are safe to batch
+    /// together in one batch, instead of one at a time.
+    /// If None, commands are only batched by [`Session::run_batch`].
+    pub auto_batch: Option<BatchPolicy>,
+    /// Check each command with [`Validate`] before sending it, and fail with
+    /// [`RunCommandError::InvalidCommand`] instead of sending it if it's invalid.
+    /// This catches mistakes without a round trip to the engine. Defaults to false.
+    pub validate_cmds: Option<bool>,
+}
+
+impl SessionBuilder {
+    /// Start a session with these engine parameters, leaving every other session option as None.
+    ///
+    /// Without the `webrtc` feature, sessions can't stream video, so [`EngineParams::webrtc`] is ignored.
+    /// The KittyCAD API client can't set [`EngineParams::order_independent_transparency`] yet,
+    /// so that's ignored too.
+    pub fn from_engine_params(client: Client, params: EngineParams) -> Self {
+        let EngineParams {
+            video_res_width,
+            video_res_height,
+            fps,
+            unlocked_framerate,
+            post_effect,
+            webrtc,
+            pool,
+            show_grid,
+            replay,
+            api_call_id,
+            order_independent_transparency: _,
+        } = params;
+        Self {
+            client,
+            fps: Some(fps),
+            unlocked_framerate: Some(unlocked_framerate),
+            video_res_height: Some(video_res_height),
+            video_res_width: Some(video_res_width),
+            buffer_reqs: None,
+            await_response_timeout: None,
+            show_grid: Some(show_grid),
+            heartbeat_interval: None,
+            reconnect: None,
+            post_effect,
+            pool,
+            replay,
+            api_call_id,
+            journal: None,
+            webrtc: Some(webrtc && cfg!(feature = "webrtc")),
+            header_auth_token: None,
+            metrics: None,
+            retry: None,
+            auto_batch: None,
+            validate_cmds: None,
+        }
+    }
+
+    /// The engine parameters this builder will start a session with.
+    /// Any parameter which isn't set is the engine's default.
+    pub fn engine_params(&self) -> EngineParams {
+        let defaults = EngineParams::default();
+        EngineParams {
+            video_res_width: self.video_res_width.unwrap_or(defaults.video_res_width),
+            video_res_height: self.video_res_height.unwrap_or(defaults.video_res_height),
+            fps: self.fps.unwrap_or(defaults.fps),
+            unlocked_framerate: self.unlocked_framerate.unwrap_or(defaults.unlocked_framerate),
+            post_effect: self.post_effect,
+            webrtc: self.webrtc.unwrap_or(false),
+            pool: self.pool.clone(),
+            show_grid: self.show_grid.unwrap_or(defaults.show_grid),
+            replay: self.replay.clone(),
+            api_call_id: self.api_call_id.clone(),
+            order_independent_transparency: defaults.order_independent_transparency,
+        }
+    }
+
+    /// Check the parameters before connecting, so that mistakes are caught without a round trip to the API.
+    fn validate(&self) -> Result<(), ApiError> {
+        if let Err(errors) = self.engine_params().validate() {
+            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+            return Err(ApiError::InvalidRequest(errors.join(", ")));
+        }
+        if cfg!(not(feature = "webrtc")) && self.webrtc == Some(true) {
+            return Err(ApiError::InvalidRequest(
+                "WebRTC needs kittycad-modeling-session's webrtc feature".to_owned(),
+            ));
+        }
+        Ok(())
+    }
 }
 
+/// Outcome of a single command in a batch: either its response,
+/// or the errors which made it fail.
+pub type BatchCmdResult = Result<OkModelingCmdResponse, Vec<kittycad_modeling_cmds::websocket::ApiError>>;
+
 /// An active session with the KittyCAD Modeling API.
-/// TODO: This needs some sort of buffering. It should allow users to send many requests in a row and then wait for the responses.
+/// Cloning a session is cheap, and the clones all share the same WebSocket connection.
+#[derive(Clone)]
 pub struct Session {
     actor_tx: mpsc::Sender<actor::Request>,
+    await_response_timeout: Duration,
+    shared: actor::Shared,
+    metrics: Option<Arc<dyn Metrics>>,
+    retry: Option<RetryPolicy>,
+    /// Check each command before sending it?
+    validate_cmds: bool,
+    /// Sends each frame of the engine's video, if the session streams it.
+    #[cfg(feature = "webrtc")]
+    video: Option<broadcast::Sender<VideoFrame>>,
 }
 
+/// How long to wait for a response, unless the session is configured otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
 impl Session {
     /// Start a session.
-    pub async fn start(
-        SessionBuilder {
+    /// Fails without connecting if the builder's parameters are invalid.
+    pub async fn start(builder: SessionBuilder) -> Result<Self, ApiError> {
+        builder.validate()?;
+        let SessionBuilder {
             client,
             fps,
             unlocked_framerate,
@@ -54,91 +235,586 @@ impl Session {
             buffer_reqs,
             await_response_timeout,
             show_grid,
-        }: SessionBuilder,
-    ) -> Result<Self, ApiError> {
-        // TODO: establish WebRTC connections for the user.
-        let webrtc = Some(false);
-        let (ws, _headers) = client
-            .modeling()
-            .commands_ws(
-                None,
-                fps,
-                None,
-                None,
-                None,
-                show_grid,
-                unlocked_framerate,
-                video_res_height,
-                video_res_width,
-                webrtc,
-            )
-            .await?;
-        // Now that we have a WebSocket connection, we can split it into two ends:
-        // one for writing to and one for reading from.
-        let (write_to_ws, read_from_ws) = tokio_tungstenite::WebSocketStream::from_raw_socket(
+            heartbeat_interval,
+            reconnect,
+            post_effect,
+            pool,
+            replay,
+            api_call_id,
+            journal,
+            webrtc,
+            header_auth_token,
+            metrics,
+            retry,
+            auto_batch,
+            validate_cmds,
+        } = builder;
+        let recorder = match journal {
+            Some(path) => Recorder::create(&path)
+                .await
+                .map_err(|e| ApiError::InvalidRequest(format!("could not create journal {}: {e}", path.display())))?,
+            None => Recorder::default(),
+        };
+        let webrtc = webrtc.unwrap_or(false);
+        // Reconnecting needs to open the WebSocket again with the same parameters,
+        // so keep a way to connect around for the actor.
+        let connect: actor::Connect = Box::new(move || {
+            let client = client.clone();
+            let api_call_id = api_call_id.clone();
+            let pool = pool.clone();
+            let replay = replay.clone();
+            async move {
+                let post_effect = post_effect.map(api_post_effect).transpose()?;
+                let (ws, _headers) = client
+                    .modeling()
+                    .commands_ws(
+                        api_call_id,
+                        fps,
+                        pool,
+                        post_effect,
+                        replay,
+                        show_grid,
+                        unlocked_framerate,
+                        video_res_height,
+                        video_res_width,
+                        Some(webrtc),
+                    )
+                    .await?;
+                let ws = tokio_tungstenite::WebSocketStream::from_raw_socket(
+                    ws,
+                    tokio_tungstenite::tungstenite::protocol::Role::Client,
+                    None,
+                )
+                .await;
+                Ok(Box::new(ws) as BoxTransport)
+            }
+            .boxed()
+        });
+        let ws = connect().await?;
+        let session = Self::spawn(
             ws,
-            tokio_tungstenite::tungstenite::protocol::Role::Client,
+            buffer_reqs,
+            recorder,
+            header_auth_token,
+            webrtc,
+            actor::Config {
+                connect: Some(connect),
+                reconnect,
+                await_response_timeout: await_response_timeout.unwrap_or(DEFAULT_TIMEOUT),
+                heartbeat_interval,
+            },
+        );
+        let actor_tx = match auto_batch {
+            Some(policy) => batcher::spawn(
+                session.actor_tx,
+                policy,
+                session.shared.clone(),
+                session.await_response_timeout,
+            ),
+            None => session.actor_tx,
+        };
+        Ok(Self {
+            actor_tx,
+            metrics,
+            retry,
+            validate_cmds: validate_cmds.unwrap_or(false),
+            ..session
+        })
+    }
+
+    /// Start a session over a WebSocket connection to the engine which you've already opened,
+    /// e.g. through a proxy, or to a self-hosted engine.
+    /// The session can't reopen this connection, so it ends if the connection drops.
+    ///
+    /// The other arguments work like the [`SessionBuilder`] fields of the same name.
+    pub fn from_stream<S: Transport>(
+        ws: S,
+        buffer_reqs: Option<usize>,
+        await_response_timeout: Option<Duration>,
+        heartbeat_interval: Option<Duration>,
+    ) -> Self {
+        Self::spawn(
+            Box::new(ws),
+            buffer_reqs,
+            Recorder::default(),
             None,
+            false,
+            actor::Config {
+                connect: None,
+                reconnect: None,
+                await_response_timeout: await_response_timeout.unwrap_or(DEFAULT_TIMEOUT),
+                heartbeat_interval,
+            },
         )
-        .await
-        .split();
+    }
+
+    /// Start the actor which runs this session's connection.
+    #[cfg_attr(not(feature = "webrtc"), allow(unused_variables))]
+    fn spawn(
+        ws: BoxTransport,
+        buffer_reqs: Option<usize>,
+        recorder: Recorder,
+        auth_token: Option<String>,
+        webrtc: bool,
+        config: actor::Config,
+    ) -> Self {
+        let shared = actor::Shared {
+            recorder,
+            auth_token: Arc::new(Mutex::new(auth_token)),
+            ..Default::default()
+        };
+        // Subscribe before the actor starts reading, so the WebRTC handshake can't miss the
+        // engine's first message.
+        #[cfg(feature = "webrtc")]
+        let events = webrtc.then(|| shared.events.subscribe());
+        let await_response_timeout = config.await_response_timeout;
         let (actor_tx, actor_rx) = mpsc::channel(buffer_reqs.unwrap_or(10));
-        tokio::task::spawn(actor::start(
-            actor_rx,
-            write_to_ws,
-            read_from_ws,
-            await_response_timeout.unwrap_or(Duration::from_secs(10)),
-        ));
-        Ok(Self { actor_tx })
+        tokio::task::spawn(actor::start(actor_rx, ws, config, shared.clone()));
+        #[cfg(feature = "webrtc")]
+        let video = events.map(|events| {
+            let frames = broadcast::channel(video::FRAME_BUFFER).0;
+            tokio::task::spawn(video::run(actor_tx.downgrade(), events, frames.clone()));
+            frames
+        });
+        Self {
+            actor_tx,
+            await_response_timeout,
+            shared,
+            metrics: None,
+            retry: None,
+            validate_cmds: false,
+            #[cfg(feature = "webrtc")]
+            video,
+        }
+    }
+
+    /// Round-trip time of the most recent heartbeat ping which the engine answered.
+    /// None if the session doesn't send heartbeats, or none have been answered yet.
+    pub fn latency(&self) -> Option<Duration> {
+        self.shared.health.latency()
+    }
+
+    /// Is the engine still responding to heartbeat pings?
+    /// Becomes false if a ping goes unanswered for a whole heartbeat interval, or the connection
+    /// closes. If the session doesn't send heartbeats, this only checks whether the connection is open.
+    pub fn is_healthy(&self) -> bool {
+        self.shared.health.is_healthy()
+    }
+
+    /// ID of the API call which this session's connection is using.
+    /// Include it when reporting problems to KittyCAD support.
+    /// None until the engine has sent it, which it does soon after connecting.
+    pub fn api_call_id(&self) -> Option<String> {
+        self.shared.api_call_id.lock().unwrap().clone()
+    }
+
+    /// Messages which the engine sends without being asked, i.e. which aren't responses to any
+    /// particular request, e.g. [`OkWebSocketResponseData::ModelingSessionData`].
+    /// The stream only includes messages sent after it was created.
+    ///
+    /// The session already answers the engine's [`OkWebSocketResponseData::MetricsRequest`]s,
+    /// so you don't need to. If a stream falls too far behind, it skips the messages it missed.
+    pub fn events(&self) -> BoxStream<'static, WebSocketResponse> {
+        let rx = self.shared.events.subscribe();
+        futures::stream::unfold(rx, |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// Frames of the engine's video stream, e.g. for capturing a turntable video without taking
+    /// a snapshot of every frame. The stream only includes frames received after it was created.
+    /// If a stream falls too far behind, it skips the frames it missed.
+    ///
+    /// The stream is empty unless the session was started with [`SessionBuilder::webrtc`].
+    /// If the WebRTC handshake fails, there's no video until the session reconnects.
+    #[cfg(feature = "webrtc")]
+    pub fn video_frames(&self) -> BoxStream<'static, VideoFrame> {
+        let Some(video) = &self.video else {
+            return futures::stream::empty().boxed();
+        };
+        futures::stream::unfold(video.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(frame) => return Some((frame, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// Authenticate with a new API token, e.g. because the old one is about to expire.
+    /// The token is sent in a [`WebSocketRequest::Headers`] request, and the session uses it for
+    /// any later connections too. Commands sent after this returns use the new token.
+    pub async fn refresh_token(&self, token: impl Into<String>) -> Result<(), RunCommandError> {
+        let token = token.into();
+        let req = actor::auth_headers(&token);
+        *self.shared.auth_token.lock().unwrap() = Some(token);
+        self.actor_tx
+            .send(actor::Request::Send(req))
+            .await
+            .map_err(|_| RunCommandError::ActorFailed)
     }
 
     /// Send a modeling command and wait for its response.
+    /// If the session has a [`RetryPolicy`], read-only commands are retried if they fail
+    /// because of a temporary problem.
     pub async fn run_command(
-        &mut self,
+        &self,
         cmd_id: ModelingCmdId,
         cmd: ModelingCmd,
     ) -> Result<OkModelingCmdResponse, RunCommandError> {
+        self.run_command_with_retries(cmd_id, cmd, None).await
+    }
+
+    /// Send a modeling command and wait for its response, for up to the given timeout,
+    /// instead of the session's usual timeout.
+    /// Like [`Session::run_command`], read-only commands might be retried, and each attempt
+    /// gets the whole timeout.
+    pub async fn run_command_with_timeout(
+        &self,
+        cmd_id: ModelingCmdId,
+        cmd: ModelingCmd,
+        timeout: Duration,
+    ) -> Result<OkModelingCmdResponse, RunCommandError> {
+        self.run_command_with_retries(cmd_id, cmd, Some(timeout)).await
+    }
+
+    /// Run a modeling command, retrying it according to the session's retry policy.
+    /// The engine won't take two commands with the same ID, so each retry gets a new one.
+    /// Responses don't include their command's ID, so the caller still sees the response as
+    /// the answer to `cmd_id`.
+    async fn run_command_with_retries(
+        &self,
+        cmd_id: ModelingCmdId,
+        cmd: ModelingCmd,
+        timeout: Option<Duration>,
+    ) -> Result<OkModelingCmdResponse, RunCommandError> {
+        let Some(retry) = self.retry.as_ref().filter(|_| cmd.is_query()) else {
+            let pending = self.send_command(cmd_id, cmd).await?;
+            return match timeout {
+                Some(timeout) => pending.with_timeout(timeout).await,
+                None => pending.await,
+            };
+        };
+        let mut attempt = 0;
+        let mut attempt_id = cmd_id;
+        loop {
+            let pending = self.send_command(attempt_id, cmd.clone()).await?;
+            let result = match timeout {
+                Some(timeout) => pending.with_timeout(timeout).await,
+                None => pending.await,
+            };
+            match result {
+                Err(e) if retry.should_retry(&cmd, &e, attempt) => {
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                    attempt += 1;
+                    attempt_id = Uuid::new_v4().into();
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send a modeling command and wait for its response.
+    /// Unlike [`Session::run_command`], this returns the command's specific output type,
+    /// so you don't have to match on the response yourself.
+    pub async fn run<C>(&self, cmd_id: ModelingCmdId, cmd: C) -> Result<C::Output, RunCommandError>
+    where
+        C: ModelingCmdVariant,
+        C::Output: TryFrom<OkModelingCmdResponse>,
+    {
+        let resp = self.run_command(cmd_id, cmd.into_enum()).await?;
+        C::Output::try_from(resp).map_err(|_| RunCommandError::ServerSentWrongType)
+    }
+
+    /// Send a modeling command, but don't wait for its response.
+    /// Returns as soon as the command has been sent. Await the returned [`PendingResponse`]
+    /// to get the command's response.
+    ///
+    /// This lets you pipeline many commands, instead of waiting a full round trip for each one
+    /// before sending the next. Responses are matched to their commands by ID, so they can be
+    /// awaited in any order.
+    ///
+    /// If the session batches commands automatically (see [`SessionBuilder::auto_batch`]),
+    /// commands which are safe to batch are held back until the batch is sent.
+    pub async fn send_command(
+        &self,
+        cmd_id: ModelingCmdId,
+        cmd: ModelingCmd,
+    ) -> Result<PendingResponse, RunCommandError> {
         // All messages to the KittyCAD Modeling API will be sent over the WebSocket as Text.
         // The text will contain JSON representing a `ModelingCmdReq`.
         // This takes in a command and its ID, and makes a WebSocket message containing that command.
-        let (tx, rx) = oneshot::channel();
-        self.actor_tx
-            .send(actor::Request::SendModelingCmd(ModelingCmdReq { cmd, cmd_id }, tx))
-            .await
-            .map_err(|_| RunCommandError::ActorFailed)?;
-        rx.await.map_err(|_| RunCommandError::ActorFailed)??;
-        let (tx, rx) = oneshot::channel();
-        self.actor_tx
-            .send(actor::Request::GetResponse(cmd_id, tx))
-            .await
-            .map_err(|_| RunCommandError::ActorFailed)?;
-        let resp = rx.await.map_err(|_| RunCommandError::ActorFailed)??;
-        Ok(resp)
+        let timeout = self.timeout_for(&cmd);
+        let req = ModelingCmdReq { cmd, cmd_id };
+        let telemetry = CommandTelemetry::cmd(&req, self.metrics.clone());
+        let resp_rx = match self.send_cmd(req).instrument(telemetry.span.clone()).await {
+            Ok(resp_rx) => resp_rx,
+            Err(e) => {
+                telemetry.finish(CommandOutcome::Error);
+                return Err(e);
+            }
+        };
+        Ok(PendingResponse::new(
+            cmd_id,
+            resp_rx,
+            timeout,
+            self.shared.clone(),
+            telemetry,
+        ))
     }
 
     /// Run a batch of commands at once.
     pub async fn run_batch_no_responses(
-        &mut self,
+        &self,
         requests: Vec<ModelingCmdReq>,
         batch_id: ModelingCmdId,
     ) -> Result<(), RunCommandError> {
+        let batch = ModelingBatch {
+            requests,
+            batch_id,
+            responses: false,
+        };
+        // There won't be a response, so as far as telemetry knows, the batch is done once it's sent.
+        let telemetry = CommandTelemetry::batch(&batch, self.metrics.clone());
+        let result = self.send_batch(batch).instrument(telemetry.span.clone()).await;
+        match &result {
+            Ok(_) => telemetry.finish(CommandOutcome::Sent),
+            Err(_) => telemetry.finish(CommandOutcome::of(&result)),
+        }
+        result?;
+        Ok(())
+    }
+
+    /// Run a batch of commands at once, and wait for each command's response.
+    /// Returns one result per request, in the same order as the requests.
+    /// If a command fails, the engine won't try any of the following commands. If the engine
+    /// didn't respond to some command (e.g. because it was never tried), then its result is an
+    /// error with no [`ApiError`](kittycad_modeling_cmds::websocket::ApiError)s.
+    pub async fn run_batch(
+        &self,
+        requests: Vec<ModelingCmdReq>,
+        batch_id: ModelingCmdId,
+    ) -> Result<Vec<BatchCmdResult>, RunCommandError> {
+        let batch = ModelingBatch {
+            requests,
+            batch_id,
+            responses: true,
+        };
+        let telemetry = CommandTelemetry::batch(&batch, self.metrics.clone());
+        let results = self.batch_results(batch).instrument(telemetry.span.clone()).await;
+        match &results {
+            Ok(results) => {
+                let outcomes: Vec<_> = results
+                    .iter()
+                    .map(|result| match result {
+                        Ok(_) => CommandOutcome::Success,
+                        // The engine didn't respond to commands it never tried.
+                        Err(errors) if errors.is_empty() => CommandOutcome::Skipped,
+                        Err(_) => CommandOutcome::Failure,
+                    })
+                    .collect();
+                let overall = if outcomes.iter().all(|outcome| *outcome == CommandOutcome::Success) {
+                    CommandOutcome::Success
+                } else {
+                    CommandOutcome::Failure
+                };
+                telemetry.finish_each(overall, outcomes);
+            }
+            Err(_) => telemetry.finish(CommandOutcome::of(&results)),
+        }
+        results
+    }
+
+    /// Send a batch, and wait for each command's result.
+    async fn batch_results(&self, batch: ModelingBatch) -> Result<Vec<BatchCmdResult>, RunCommandError> {
+        let batch_id = batch.batch_id;
+        let cmd_ids: Vec<_> = batch.requests.iter().map(|req| req.cmd_id).collect();
+        // The engine only responds once it's run every command in the batch.
+        let timeout = batch
+            .requests
+            .iter()
+            .map(|req| self.timeout_for(&req.cmd))
+            .max()
+            .unwrap_or(self.await_response_timeout);
+        let rx = self.send_batch(batch).await?;
+        let mut responses = match AwaitResponse::new(batch_id, rx, timeout, self.shared.clone()).await? {
+            WebSocketResponse::Success(s) => match s.resp {
+                OkWebSocketResponseData::ModelingBatch { responses } => responses,
+                // This request ID should be for a batch. Something's gone very wrong.
+                _ => return Err(RunCommandError::ServerSentWrongType),
+            },
+            WebSocketResponse::Failure(e) => {
+                return Err(RunCommandError::modeling_api_failure(Some(batch_id.into()), e.errors))
+            }
+        };
+        let results = cmd_ids
+            .into_iter()
+            .map(|cmd_id| match responses.remove(&cmd_id) {
+                Some(BatchResponse::Success { response }) => Ok(response),
+                Some(BatchResponse::Failure { errors }) => Err(errors),
+                None => Err(Vec::new()),
+            })
+            .collect();
+        Ok(results)
+    }
+
+    /// Export entities from the scene as files in the given format.
+    /// If `entity_ids` is empty, the whole scene is exported.
+    pub async fn export(&self, entity_ids: Vec<Uuid>, format: OutputFormat3d) -> Result<Vec<RawFile>, RunCommandError> {
+        let cmd = Export::builder().entity_ids(entity_ids).format(format).build();
+        let output = self.run(Uuid::new_v4().into(), cmd).await?;
+        Ok(output.files.into_iter().map(RawFile::from).collect())
+    }
+
+    /// Export entities from the scene as files in the given format, and save them into the given directory.
+    /// If `entity_ids` is empty, the whole scene is exported.
+    /// Returns the path of each file saved.
+    pub async fn export_to_dir(
+        &self,
+        entity_ids: Vec<Uuid>,
+        format: OutputFormat3d,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, RunCommandError> {
+        let files = self.export(entity_ids, format).await?;
+        let mut paths = Vec::with_capacity(files.len());
+        for file in files {
+            // Only use the file's name, so the engine can't write files outside the directory.
+            let Some(name) = Path::new(&file.name).file_name() else {
+                return Err(RunCommandError::WriteExport(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("exported file has an invalid name: {:?}", file.name),
+                )));
+            };
+            let path = dir.as_ref().join(name);
+            tokio::fs::write(&path, file.contents)
+                .await
+                .map_err(RunCommandError::WriteExport)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Re-send every modeling command in the journal to this session's engine, one at a time,
+    /// and compare each response to the one in the journal.
+    /// Returns every response which differs from the journal.
+    ///
+    /// Other requests, like pings, aren't replayed. If the journal has no response for some
+    /// command (e.g. a batch which didn't ask for responses), it's sent without waiting for one.
+    pub async fn replay(&self, journal: &Journal) -> Result<Vec<ReplayMismatch>, RunCommandError> {
+        let mut mismatches = Vec::new();
+        for entry in &journal.entries {
+            let JournalMessage::Sent(req) = &entry.message else {
+                continue;
+            };
+            let (id, timeout, rx) = match req.clone() {
+                WebSocketRequest::ModelingCmdReq(req) => {
+                    (req.cmd_id, self.timeout_for(&req.cmd), self.send_cmd(req).await?)
+                }
+                WebSocketRequest::ModelingCmdBatchReq(batch) => {
+                    let timeout = batch
+                        .requests
+                        .iter()
+                        .map(|req| self.timeout_for(&req.cmd))
+                        .max()
+                        .unwrap_or(self.await_response_timeout);
+                    (batch.batch_id, timeout, self.send_batch(batch).await?)
+                }
+                _ => continue,
+            };
+            let Some(recorded) = journal.response_to(id.into()) else {
+                continue;
+            };
+            let replayed = match AwaitResponse::new(id, rx, timeout, self.shared.clone()).await {
+                Ok(resp) => Some(resp),
+                Err(RunCommandError::TimeOutWaitingForResponse) => None,
+                Err(e) => return Err(e),
+            };
+            // Neither response type can be compared directly, but their JSON can be.
+            let same = replayed
+                .as_ref()
+                .is_some_and(|replayed| serde_json::to_value(replayed).ok() == serde_json::to_value(recorded).ok());
+            if !same {
+                mismatches.push(ReplayMismatch {
+                    request_id: id.into(),
+                    recorded: recorded.clone(),
+                    replayed,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// How long to wait for this command's response.
+    fn timeout_for(&self, cmd: &ModelingCmd) -> Duration {
+        timeout::timeout_for(cmd, self.await_response_timeout)
+    }
+
+    /// Validate the command, if the session is configured to.
+    #[allow(clippy::result_large_err)]
+    fn check(&self, req: &ModelingCmdReq) -> Result<(), RunCommandError> {
+        if !self.validate_cmds {
+            return Ok(());
+        }
+        req.cmd.validate().map_err(|errors| RunCommandError::InvalidCommand {
+            cmd_id: req.cmd_id.into(),
+            errors,
+        })
+    }
+
+    /// Send a modeling command, and get a receiver for its response.
+    async fn send_cmd(&self, req: ModelingCmdReq) -> Result<actor::ResponseReceiver, RunCommandError> {
+        self.check(&req)?;
         let (tx, rx) = oneshot::channel();
         self.actor_tx
-            .send(actor::Request::SendModelingBatch(
-                ModelingBatch {
-                    requests,
-                    batch_id,
-                    responses: false,
-                },
-                tx,
-            ))
+            .send(actor::Request::SendModelingCmd(req, tx))
             .await
             .map_err(|_| RunCommandError::ActorFailed)?;
-        rx.await.map_err(|_| RunCommandError::ActorFailed)??;
-        Ok(())
+        rx.await.map_err(|_| RunCommandError::ActorFailed)?
+    }
+
+    /// Send a batch of commands, and get a receiver for the batch's response.
+    async fn send_batch(&self, batch: ModelingBatch) -> Result<actor::ResponseReceiver, RunCommandError> {
+        for req in &batch.requests {
+            self.check(req)?;
+        }
+        let (tx, rx) = oneshot::channel();
+        self.actor_tx
+            .send(actor::Request::SendModelingBatch(batch, tx))
+            .await
+            .map_err(|_| RunCommandError::ActorFailed)?;
+        rx.await.map_err(|_| RunCommandError::ActorFailed)?
     }
 }
 
+/// A replayed request whose response didn't match the journal.
+#[derive(Debug, Clone)]
+pub struct ReplayMismatch {
+    /// ID of the request.
+    pub request_id: Uuid,
+    /// The response in the journal.
+    pub recorded: WebSocketResponse,
+    /// The response when the request was replayed, or None if it timed out.
+    pub replayed: Option<WebSocketResponse>,
+}
+
+/// Convert the post effect into the KittyCAD API client's equivalent type.
+fn api_post_effect(effect: PostEffectType) -> Result<kittycad::types::PostEffectType, ApiError> {
+    // Both types come from the same API schema, so they have the same serialized form.
+    serde_json::to_value(effect)
+        .and_then(serde_json::from_value)
+        .map_err(|e| ApiError::InvalidRequest(format!("unsupported post effect: {e}")))
+}
+
 /// Errors from running a modeling command.
 #[derive(thiserror::Error, Debug)]
 pub enum RunCommandError {
@@ -162,6 +838,15 @@ pub enum RunCommandError {
         /// Errors that caused the request to fail.
         errors: Vec<kittycad_modeling_cmds::websocket::ApiError>,
     },
+    /// The engine rejected the session's API token, because it was missing, invalid or expired.
+    /// Refresh the token with [`Session::refresh_token`], then try again.
+    #[error("modeling API rejected the auth token on request {request_id:?}: {errors:?}")]
+    AuthFailed {
+        /// ID of the failed request.
+        request_id: Option<Uuid>,
+        /// Errors that caused the request to fail.
+        errors: Vec<kittycad_modeling_cmds::websocket::ApiError>,
+    },
     /// WebSocket closed unexpectedly.
     #[error("WebSocket closed unexpectedly")]
     WebSocketClosed,
@@ -177,23 +862,65 @@ pub enum RunCommandError {
     /// Actor has failed
     #[error("Websocket actor has failed, restart the session")]
     ActorFailed,
+    /// The WebSocket connection dropped and the session reconnected before the response arrived.
+    /// The command might or might not have been run, so check the scene before retrying it.
+    #[error("WebSocket reconnected before the response arrived")]
+    Reconnected,
+    /// The session reconnected, but couldn't replay its earlier commands on the new connection.
+    #[error("could not replay earlier commands after reconnecting, restart the session")]
+    ReplayFailed,
+    /// Timed out waiting for a response, and the engine has stopped answering heartbeat pings too.
+    #[error("engine stopped responding, restart the session")]
+    EngineUnresponsive,
+    /// Could not save an exported file.
+    #[error("could not save exported file: {0}")]
+    WriteExport(std::io::Error),
+    /// The command is invalid, so it wasn't sent.
+    #[error("command {cmd_id} is invalid: {errors:?}")]
+    InvalidCommand {
+        /// ID of the invalid command.
+        cmd_id: Uuid,
+        /// Everything wrong with the command.
+        errors: Vec<ValidationError>,
+    },
 }
 
 impl RunCommandError {
+    /// The engine failed this request with these errors.
+    pub(crate) fn modeling_api_failure(
+        request_id: Option<Uuid>,
+        errors: Vec<kittycad_modeling_cmds::websocket::ApiError>,
+    ) -> Self {
+        let auth_failed = errors
+            .iter()
+            .any(|e| matches!(e.error_code, ErrorCode::AuthTokenMissing | ErrorCode::AuthTokenInvalid));
+        if auth_failed {
+            Self::AuthFailed { request_id, errors }
+        } else {
+            Self::ModelingApiFailure { request_id, errors }
+        }
+    }
+
     /// Does this error indicate that the session has become unhealthy and should be restarted
     /// (i.e. ended and started again)?
     pub fn should_end_session(&self) -> bool {
         match self {
             RunCommandError::WebSocketClosed => true,
             RunCommandError::ActorFailed => true,
+            RunCommandError::ReplayFailed => true,
+            RunCommandError::EngineUnresponsive => true,
             RunCommandError::ApiError(_) => false,
             RunCommandError::InvalidRequestBody(_) => false,
             RunCommandError::WebSocketSend(_) => false,
             RunCommandError::WebSocketRecv(_) => false,
             RunCommandError::ModelingApiFailure { .. } => false,
+            RunCommandError::AuthFailed { .. } => false,
             RunCommandError::WrongId => false,
             RunCommandError::TimeOutWaitingForResponse => false,
             RunCommandError::ServerSentWrongType => false,
+            RunCommandError::Reconnected => false,
+            RunCommandError::WriteExport(_) => false,
+            RunCommandError::InvalidCommand { .. } => false,
         }
     }
 }
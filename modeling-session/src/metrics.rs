@@ -0,0 +1,158 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use kittycad_modeling_cmds::{
+    websocket::{ModelingBatch, ModelingCmdReq},
+    ModelingCmdEndpoint,
+};
+use tracing::field::Empty;
+
+use crate::RunCommandError;
+
+/// Collects metrics about the modeling commands a session runs, e.g. to export them to Prometheus.
+/// Set it with [`SessionBuilder::metrics`](crate::SessionBuilder::metrics).
+///
+/// Each method is called once per command, including each command in a batch, so they're a good
+/// place to increment counters and record histograms. They're called on the session's hot path,
+/// so they shouldn't block. Every method does nothing by default.
+pub trait Metrics: Send + Sync {
+    /// A command was sent to the engine. Its payload is the size of the request, in bytes of JSON.
+    /// For commands in a batch, it's the size of the whole batch.
+    fn command_sent(&self, endpoint: &ModelingCmdEndpoint, payload_bytes: usize) {
+        let _ = (endpoint, payload_bytes);
+    }
+
+    /// A command finished, this long after it was sent.
+    /// For commands in a batch, the latency is the whole batch's.
+    fn command_finished(&self, endpoint: &ModelingCmdEndpoint, outcome: CommandOutcome, latency: Duration) {
+        let _ = (endpoint, outcome, latency);
+    }
+}
+
+/// How a modeling command finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandOutcome {
+    /// The engine ran the command.
+    Success,
+    /// The engine reported that the command failed.
+    Failure,
+    /// The response didn't arrive in time.
+    TimedOut,
+    /// The command was in a batch, and the engine didn't try it because an earlier command failed.
+    Skipped,
+    /// Something else went wrong, e.g. the connection closed.
+    Error,
+    /// The command was sent without asking for a response, so how it went is unknown.
+    Sent,
+}
+
+impl CommandOutcome {
+    /// A short name for this outcome, e.g. to label metrics with.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::TimedOut => "timed_out",
+            Self::Skipped => "skipped",
+            Self::Error => "error",
+            Self::Sent => "sent",
+        }
+    }
+
+    /// How a command which returned this result finished.
+    pub(crate) fn of<T>(result: &Result<T, RunCommandError>) -> Self {
+        match result {
+            Ok(_) => Self::Success,
+            Err(RunCommandError::ModelingApiFailure { .. } | RunCommandError::AuthFailed { .. }) => Self::Failure,
+            Err(RunCommandError::TimeOutWaitingForResponse | RunCommandError::EngineUnresponsive) => Self::TimedOut,
+            Err(_) => Self::Error,
+        }
+    }
+}
+
+/// Reports on a command, or a batch of commands, from when it's sent until it finishes.
+/// Holds a tracing span for the request, and reports to the session's metrics.
+pub(crate) struct CommandTelemetry {
+    pub(crate) span: tracing::Span,
+    endpoints: Vec<ModelingCmdEndpoint>,
+    metrics: Option<Arc<dyn Metrics>>,
+    sent_at: Instant,
+}
+
+impl CommandTelemetry {
+    /// Start reporting on a command which is about to be sent.
+    pub(crate) fn cmd(req: &ModelingCmdReq, metrics: Option<Arc<dyn Metrics>>) -> Self {
+        let endpoint = ModelingCmdEndpoint::from(&req.cmd);
+        let span = tracing::info_span!(
+            "modeling_cmd",
+            endpoint = %endpoint,
+            cmd_id = %req.cmd_id,
+            payload_bytes = Empty,
+            latency_ms = Empty,
+            outcome = Empty,
+        );
+        Self::start(span, vec![endpoint], metrics, req)
+    }
+
+    /// Start reporting on a batch which is about to be sent.
+    pub(crate) fn batch(batch: &ModelingBatch, metrics: Option<Arc<dyn Metrics>>) -> Self {
+        let span = tracing::info_span!(
+            "modeling_batch",
+            batch_id = %batch.batch_id,
+            cmds = batch.requests.len(),
+            payload_bytes = Empty,
+            latency_ms = Empty,
+            outcome = Empty,
+        );
+        let endpoints = batch
+            .requests
+            .iter()
+            .map(|req| ModelingCmdEndpoint::from(&req.cmd))
+            .collect();
+        Self::start(span, endpoints, metrics, batch)
+    }
+
+    fn start(
+        span: tracing::Span,
+        endpoints: Vec<ModelingCmdEndpoint>,
+        metrics: Option<Arc<dyn Metrics>>,
+        payload: &impl serde::Serialize,
+    ) -> Self {
+        // Measuring the payload means serializing it an extra time, so only do that if anyone's listening.
+        if metrics.is_some() || !span.is_disabled() {
+            let payload_bytes = serde_json::to_vec(payload).map_or(0, |payload| payload.len());
+            span.record("payload_bytes", payload_bytes);
+            if let Some(metrics) = &metrics {
+                for endpoint in &endpoints {
+                    metrics.command_sent(endpoint, payload_bytes);
+                }
+            }
+        }
+        Self {
+            span,
+            endpoints,
+            metrics,
+            sent_at: Instant::now(),
+        }
+    }
+
+    /// Report that every command finished the same way.
+    pub(crate) fn finish(&self, outcome: CommandOutcome) {
+        self.finish_each(outcome, std::iter::repeat(outcome));
+    }
+
+    /// Report that the request finished with the given overall outcome, and each of its commands
+    /// with the given outcomes, in the order they were sent.
+    pub(crate) fn finish_each(&self, overall: CommandOutcome, outcomes: impl IntoIterator<Item = CommandOutcome>) {
+        let latency = self.sent_at.elapsed();
+        self.span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+        self.span.record("outcome", overall.as_str());
+        if let Some(metrics) = &self.metrics {
+            for (endpoint, outcome) in self.endpoints.iter().zip(outcomes) {
+                metrics.command_finished(endpoint, outcome, latency);
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use kittycad_modeling_cmds::{
+    id::ModelingCmdId,
+    ok_response::{output, OkModelingCmdResponse},
+    websocket::{OkWebSocketResponseData, WebSocketResponse},
+};
+use tokio::time::Sleep;
+
+use crate::{
+    actor::{ResponseReceiver, Shared},
+    metrics::{CommandOutcome, CommandTelemetry},
+    RunCommandError,
+};
+
+/// A modeling command which has been sent, but whose response might not have arrived yet.
+/// Await it to get the response.
+/// Dropping it cancels waiting for the response, although the engine still runs the command.
+pub struct PendingResponse {
+    cmd_id: ModelingCmdId,
+    inner: AwaitResponse,
+    telemetry: CommandTelemetry,
+}
+
+impl PendingResponse {
+    pub(crate) fn new(
+        cmd_id: ModelingCmdId,
+        rx: ResponseReceiver,
+        timeout: Duration,
+        shared: Shared,
+        telemetry: CommandTelemetry,
+    ) -> Self {
+        Self {
+            cmd_id,
+            inner: AwaitResponse::new(cmd_id, rx, timeout, shared),
+            telemetry,
+        }
+    }
+
+    /// Wait this long for the response, counting from now, instead of the usual timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.timeout = Box::pin(tokio::time::sleep(timeout));
+        self
+    }
+
+    /// ID of the command this response is for.
+    pub fn cmd_id(&self) -> ModelingCmdId {
+        self.cmd_id
+    }
+}
+
+impl Future for PendingResponse {
+    type Output = Result<OkModelingCmdResponse, RunCommandError>;
+
+    #[allow(clippy::result_large_err)]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let cmd_id = self.cmd_id;
+        let span = self.telemetry.span.clone();
+        let _entered = span.enter();
+        let Poll::Ready(resp) = Pin::new(&mut self.inner).poll(cx) else {
+            return Poll::Pending;
+        };
+        let resp = resp.and_then(|resp| modeling_response(cmd_id, resp));
+        self.telemetry.finish(CommandOutcome::of(&resp));
+        Poll::Ready(resp)
+    }
+}
+
+/// Waits for the WebSocket response to some request, until it times out.
+/// If it's dropped before the response arrives, the actor stops waiting for the response too.
+pub(crate) struct AwaitResponse {
+    id: ModelingCmdId,
+    rx: ResponseReceiver,
+    timeout: Pin<Box<Sleep>>,
+    shared: Shared,
+    received: bool,
+}
+
+impl AwaitResponse {
+    pub(crate) fn new(id: ModelingCmdId, rx: ResponseReceiver, timeout: Duration, shared: Shared) -> Self {
+        Self {
+            id,
+            rx,
+            timeout: Box::pin(tokio::time::sleep(timeout)),
+            shared,
+            received: false,
+        }
+    }
+}
+
+impl Drop for AwaitResponse {
+    fn drop(&mut self) {
+        if !self.received {
+            self.shared.in_flight.lock().unwrap().cancel(self.id);
+        }
+    }
+}
+
+impl Future for AwaitResponse {
+    type Output = Result<WebSocketResponse, RunCommandError>;
+
+    #[allow(clippy::result_large_err)]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(resp) = Pin::new(&mut self.rx).poll(cx) {
+            self.received = true;
+            let resp = resp.map_err(|_| RunCommandError::ActorFailed).and_then(|resp| resp);
+            return Poll::Ready(resp);
+        }
+        if self.timeout.as_mut().poll(cx).is_ready() {
+            // A slow command is one thing, but if the engine isn't answering pings either,
+            // the session is probably dead.
+            let err = if self.shared.health.is_healthy() {
+                RunCommandError::TimeOutWaitingForResponse
+            } else {
+                RunCommandError::EngineUnresponsive
+            };
+            return Poll::Ready(Err(err));
+        }
+        Poll::Pending
+    }
+}
+
+/// Get the modeling command's response out of the WebSocket response.
+#[allow(clippy::result_large_err)]
+fn modeling_response(cmd_id: ModelingCmdId, resp: WebSocketResponse) -> Result<OkModelingCmdResponse, RunCommandError> {
+    match resp {
+        WebSocketResponse::Success(s) => match s.resp {
+            OkWebSocketResponseData::Modeling { modeling_response } => Ok(modeling_response),
+            // Exported files arrive in their own kind of response, but they're still the output
+            // of the `Export` command.
+            OkWebSocketResponseData::Export { files } => Ok(OkModelingCmdResponse::Export(output::Export {
+                files: files.into_iter().map(Into::into).collect(),
+            })),
+            // This request ID should be for a modeling request. Something's gone very wrong.
+            _ => Err(RunCommandError::ServerSentWrongType),
+        },
+        WebSocketResponse::Failure(e) => Err(RunCommandError::modeling_api_failure(Some(cmd_id.into()), e.errors)),
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use kittycad::types::error::Error as ApiError;
+use kittycad_modeling_cmds::{ModelingCmd, SceneClearAll};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+use crate::{RunCommandError, Session, SessionBuilder};
+
+/// Keeps several sessions warm, and leases them out one job at a time.
+///
+/// When a lease is dropped, its session's scene is cleared, and the session goes back into the pool
+/// for the next lease. Sessions which have become unhealthy are ended instead, and replaced the next
+/// time a lease needs one.
+/// Cloning a pool is cheap, and the clones all share the same sessions.
+#[derive(Clone)]
+pub struct SessionPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    builder: SessionBuilder,
+    size: usize,
+    /// One permit per session, whether it's idle, leased, or hasn't been started yet.
+    permits: Arc<Semaphore>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Sessions which are ready to be leased.
+    idle: Vec<Session>,
+    metrics: PoolMetrics,
+}
+
+/// Statistics about a [`SessionPool`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Sessions which are ready to be leased right now.
+    pub idle: usize,
+    /// Sessions which are leased right now.
+    pub leased: usize,
+    /// How many sessions the pool has started, including replacements for evicted sessions.
+    pub started: u64,
+    /// How many sessions the pool has ended because they became unhealthy, or their scene
+    /// couldn't be cleared.
+    pub evicted: u64,
+    /// How many leases the pool has handed out.
+    pub leases: u64,
+}
+
+impl SessionPool {
+    /// Start `size` sessions, each from a copy of the builder.
+    /// A pool needs at least one session, or leasing would wait forever.
+    pub async fn start(builder: SessionBuilder, size: usize) -> Result<Self, ApiError> {
+        if size == 0 {
+            return Err(ApiError::InvalidRequest(
+                "a session pool needs at least one session".to_owned(),
+            ));
+        }
+        let sessions = futures::future::try_join_all((0..size).map(|_| Session::start(builder.clone()))).await?;
+        let state = State {
+            metrics: PoolMetrics {
+                idle: sessions.len(),
+                started: sessions.len() as u64,
+                ..Default::default()
+            },
+            idle: sessions,
+        };
+        Ok(Self {
+            inner: Arc::new(Inner {
+                builder,
+                size,
+                permits: Arc::new(Semaphore::new(size)),
+                state: Mutex::new(state),
+            }),
+        })
+    }
+
+    /// Wait until a session is free, then lease it.
+    /// Idle sessions which have become unhealthy are evicted. If there's no healthy session left,
+    /// this starts a replacement.
+    pub async fn lease(&self) -> Result<SessionLease, ApiError> {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the pool never closes its semaphore");
+        let idle = {
+            let mut state = self.inner.state.lock().unwrap();
+            // A session's connection can close while it's idle. Then it's no use to anyone,
+            // so it's evicted, and replaced if there are no healthy sessions left.
+            let mut healthy = None;
+            while let Some(session) = state.idle.pop() {
+                if session.is_healthy() {
+                    healthy = Some(session);
+                    break;
+                }
+                state.metrics.evicted += 1;
+            }
+            healthy
+        };
+        let session = match idle {
+            Some(session) => session,
+            None => {
+                // If this fails, the permit is dropped, so another lease can try again.
+                let session = Session::start(self.inner.builder.clone()).await?;
+                self.inner.state.lock().unwrap().metrics.started += 1;
+                session
+            }
+        };
+        let mut state = self.inner.state.lock().unwrap();
+        state.metrics.idle = state.idle.len();
+        state.metrics.leased += 1;
+        state.metrics.leases += 1;
+        Ok(SessionLease {
+            session: Some(session),
+            permit: Some(permit),
+            evict: false,
+            pool: self.inner.clone(),
+        })
+    }
+
+    /// How many sessions the pool keeps.
+    pub fn size(&self) -> usize {
+        self.inner.size
+    }
+
+    /// Statistics about the pool right now.
+    pub fn metrics(&self) -> PoolMetrics {
+        self.inner.state.lock().unwrap().metrics.clone()
+    }
+}
+
+/// A session leased from a [`SessionPool`].
+/// Use it like a [`Session`]. When it's dropped, the session goes back into the pool.
+pub struct SessionLease {
+    session: Option<Session>,
+    permit: Option<OwnedSemaphorePermit>,
+    /// Should the session be ended, instead of going back into the pool?
+    evict: bool,
+    pool: Arc<Inner>,
+}
+
+impl SessionLease {
+    /// Tell the pool about an error from this session.
+    /// If the error means the session should end, it won't go back into the pool.
+    pub fn report(&mut self, err: &RunCommandError) {
+        if err.should_end_session() {
+            self.evict = true;
+        }
+    }
+
+    /// End this session instead of putting it back into the pool.
+    pub fn evict(mut self) {
+        self.evict = true;
+    }
+}
+
+impl Deref for SessionLease {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        self.session
+            .as_ref()
+            .expect("the session is only taken when the lease is dropped")
+    }
+}
+
+impl Drop for SessionLease {
+    fn drop(&mut self) {
+        let (Some(session), Some(permit)) = (self.session.take(), self.permit.take()) else {
+            return;
+        };
+        let pool = self.pool.clone();
+        let evict = self.evict || !session.is_healthy();
+        // Clearing the scene needs a round trip to the engine, which can't happen in `drop`.
+        // Without a runtime (e.g. on a blocking thread, or after the runtime shut down), it can't
+        // happen at all, so the session is evicted instead.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            pool.put_back(session, false, permit);
+            return;
+        };
+        // Keep the permit until it's done, so the next lease doesn't get a half-cleared session.
+        runtime.spawn(async move {
+            let reusable = !evict
+                && session
+                    .run_command(
+                        Uuid::new_v4().into(),
+                        ModelingCmd::SceneClearAll(SceneClearAll::default()),
+                    )
+                    .await
+                    .is_ok();
+            pool.put_back(session, reusable, permit);
+        });
+    }
+}
+
+impl Inner {
+    /// Finish a lease, either returning its session to the pool or evicting it.
+    fn put_back(&self, session: Session, reusable: bool, permit: OwnedSemaphorePermit) {
+        let mut state = self.state.lock().unwrap();
+        state.metrics.leased -= 1;
+        if reusable {
+            state.idle.push(session);
+        } else {
+            state.metrics.evicted += 1;
+        }
+        state.metrics.idle = state.idle.len();
+        drop(permit);
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+/// How a session should reconnect when its WebSocket connection drops.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Give up reconnecting after this many failed attempts.
+    pub max_attempts: u32,
+    /// How long to wait before the first attempt to reconnect.
+    /// This doubles after each failed attempt.
+    pub initial_backoff: Duration,
+    /// The longest to wait between two attempts to reconnect.
+    pub max_backoff: Duration,
+    /// If true, the session records every modeling command which the engine acknowledged
+    /// and which changes the scene or how it's displayed, i.e. everything except
+    /// [queries](kittycad_modeling_cmds::ModelingCmd::is_query) and
+    /// [camera moves](kittycad_modeling_cmds::ModelingCmd::affects_camera_only).
+    /// After reconnecting, it re-sends them all, so that the new engine's scene matches the old one.
+    pub replay_journal: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            replay_journal: true,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// How long to wait before the given attempt (starting at 0) to reconnect.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_max() {
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..Default::default()
+        };
+        let backoffs: Vec<_> = (0..5).map(|attempt| policy.backoff(attempt).as_secs()).collect();
+        assert_eq!(backoffs, vec![1, 2, 4, 5, 5]);
+        // Shouldn't overflow, even after lots of attempts.
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use kittycad_modeling_cmds::ModelingCmd;
+
+use crate::RunCommandError;
+
+/// How a session should retry commands which failed because of a temporary problem in the engine
+/// or API, i.e. an [`ErrorCode`](kittycad_modeling_cmds::websocket::ErrorCode) which
+/// [is retryable](kittycad_modeling_cmds::websocket::ErrorCode::is_retryable).
+///
+/// Only [read-only](ModelingCmd::is_query) commands are retried, because they give the same
+/// result however many times they run. Commands which change the scene are never retried,
+/// because the engine might have partly run them before failing.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Give up after retrying this many times.
+    pub max_retries: u32,
+    /// Roughly how long to wait before the first retry.
+    /// This doubles after each failed retry.
+    pub initial_backoff: Duration,
+    /// The longest to wait between two retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Should this command be retried, after failing this way on the given attempt (starting at 0)?
+    pub(crate) fn should_retry(&self, cmd: &ModelingCmd, err: &RunCommandError, attempt: u32) -> bool {
+        let RunCommandError::ModelingApiFailure { errors, .. } = err else {
+            return false;
+        };
+        attempt < self.max_retries
+            && cmd.is_query()
+            && !errors.is_empty()
+            && errors.iter().all(|e| e.error_code.is_retryable())
+    }
+
+    /// How long to wait before the given retry (starting at 0).
+    /// Each wait is jittered, so that many sessions which failed at the same time don't all retry
+    /// at the same time too.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff_with_jitter(attempt, fastrand::f64())
+    }
+
+    /// The backoff, scaled between half and all of it by `jitter`, which is between 0 and 1.
+    fn backoff_with_jitter(&self, attempt: u32, jitter: f64) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        let backoff = self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+        backoff.mul_f64(0.5 + jitter / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kittycad_modeling_cmds::{
+        websocket::{ApiError, ErrorCode},
+        GetNumObjects, StartPath,
+    };
+
+    use super::*;
+
+    fn failure(error_code: ErrorCode) -> RunCommandError {
+        RunCommandError::ModelingApiFailure {
+            request_id: None,
+            errors: vec![ApiError {
+                error_code,
+                message: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn only_retries_read_only_cmds_on_retryable_errors() {
+        let policy = RetryPolicy::default();
+        let query = ModelingCmd::GetNumObjects(GetNumObjects::default());
+        let mutation = ModelingCmd::StartPath(StartPath::default());
+        assert!(policy.should_retry(&query, &failure(ErrorCode::InternalEngine), 0));
+        assert!(!policy.should_retry(&query, &failure(ErrorCode::BadRequest), 0));
+        assert!(!policy.should_retry(&query, &RunCommandError::WebSocketClosed, 0));
+        assert!(!policy.should_retry(&mutation, &failure(ErrorCode::InternalEngine), 0));
+        assert!(!policy.should_retry(&query, &failure(ErrorCode::InternalEngine), policy.max_retries));
+    }
+
+    #[test]
+    fn backoff_is_jittered() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff_with_jitter(0, 0.0), Duration::from_millis(500));
+        assert_eq!(policy.backoff_with_jitter(0, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff_with_jitter(1, 1.0), Duration::from_secs(2));
+        assert_eq!(policy.backoff_with_jitter(5, 1.0), Duration::from_secs(3));
+    }
+}
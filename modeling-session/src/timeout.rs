@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use kittycad_modeling_cmds::ModelingCmd;
+
+/// Importing or exporting a big model can take minutes.
+const FILE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Operations which compute new geometry from complicated solids can take a while.
+const GEOMETRY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Some commands are known to be slow, so the session waits at least this long for their response,
+/// even if its usual timeout is shorter.
+pub(crate) fn min_timeout(cmd: &ModelingCmd) -> Option<Duration> {
+    match cmd {
+        ModelingCmd::ImportFiles(_) | ModelingCmd::Export(_) | ModelingCmd::Export2d(_) | ModelingCmd::Export3d(_) => {
+            Some(FILE_TIMEOUT)
+        }
+        ModelingCmd::BooleanUnion(_)
+        | ModelingCmd::BooleanIntersection(_)
+        | ModelingCmd::BooleanSubtract(_)
+        | ModelingCmd::BooleanImprint(_)
+        | ModelingCmd::Loft(_)
+        | ModelingCmd::Sweep(_)
+        | ModelingCmd::Revolve(_)
+        | ModelingCmd::Solid3dFilletEdge(_) => Some(GEOMETRY_TIMEOUT),
+        _ => None,
+    }
+}
+
+/// How long to wait for this command's response, if the session usually waits for the given timeout.
+pub(crate) fn timeout_for(cmd: &ModelingCmd, await_response_timeout: Duration) -> Duration {
+    min_timeout(cmd).map_or(await_response_timeout, |min| min.max(await_response_timeout))
+}
@@ -0,0 +1,19 @@
+use futures::{Sink, Stream};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message as WsMsg};
+
+/// A WebSocket connection to the KittyCAD engine, which a [`Session`](crate::Session) can run over.
+///
+/// This is implemented for any stream of WebSocket messages which is also a sink for them, e.g.
+/// [`tokio_tungstenite::WebSocketStream`] over a TCP stream, a TLS proxy or an in-memory duplex.
+pub trait Transport:
+    Stream<Item = Result<WsMsg, WsError>> + Sink<WsMsg, Error = WsError> + Send + Unpin + 'static
+{
+}
+
+impl<T> Transport for T where
+    T: Stream<Item = Result<WsMsg, WsError>> + Sink<WsMsg, Error = WsError> + Send + Unpin + 'static
+{
+}
+
+/// A transport whose type has been erased, so that sessions don't depend on how they connected.
+pub(crate) type BoxTransport = Box<dyn Transport>;
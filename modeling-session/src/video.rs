@@ -0,0 +1,227 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use kittycad_modeling_cmds::websocket::{IceServer, OkWebSocketResponseData, WebSocketRequest, WebSocketResponse};
+use tokio::sync::{broadcast, mpsc};
+use webrtc::{
+    api::{interceptor_registry::register_default_interceptors, media_engine::MediaEngine, APIBuilder},
+    ice_transport::{
+        ice_candidate::{RTCIceCandidate, RTCIceCandidateInit},
+        ice_server::RTCIceServer,
+    },
+    interceptor::registry::Registry,
+    media::io::sample_builder::SampleBuilder,
+    peer_connection::{
+        configuration::RTCConfiguration, sdp::session_description::RTCSessionDescription, RTCPeerConnection,
+    },
+    rtp::codecs::h264::H264Packet,
+    rtp_transceiver::{
+        rtp_codec::RTPCodecType, rtp_transceiver_direction::RTCRtpTransceiverDirection, RTCRtpTransceiverInit,
+    },
+    track::track_remote::TrackRemote,
+};
+
+use crate::actor::Request;
+
+/// A frame of the engine's video stream.
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    /// The frame, as H.264 in Annex B format (i.e. NAL units separated by start codes).
+    /// Most video tools can decode or mux this directly, e.g. `ffmpeg -f h264 -i frames.h264`.
+    pub data: Vec<u8>,
+    /// When the frame was received.
+    pub timestamp: SystemTime,
+    /// How long the frame should be shown for.
+    pub duration: Duration,
+    /// Can this frame be decoded without any earlier frames?
+    pub is_keyframe: bool,
+}
+
+/// How many frames can be buffered for each video stream, before the slowest stream misses some.
+pub(crate) const FRAME_BUFFER: usize = 64;
+
+/// How many RTP packets to hold while waiting for late packets, before giving up on them.
+const MAX_LATE_PACKETS: u16 = 512;
+
+/// Run the WebRTC handshake with the engine, whenever it sends its ICE servers, and send every
+/// video frame it streams back to `frames`.
+/// The engine sends its ICE servers again after reconnecting, so this negotiates a new peer
+/// connection each time.
+/// Runs until the session's events end.
+pub(crate) async fn run(
+    actor_tx: mpsc::WeakSender<Request>,
+    mut events: broadcast::Receiver<WebSocketResponse>,
+    frames: broadcast::Sender<VideoFrame>,
+) {
+    let mut peer: Option<Arc<RTCPeerConnection>> = None;
+    // ICE candidates which the engine sent before its answer. The peer connection can't use
+    // them until it has the answer, so they wait here.
+    let mut early_candidates: Vec<RTCIceCandidateInit> = Vec::new();
+    loop {
+        let event = match events.recv().await {
+            Ok(WebSocketResponse::Success(s)) => s.resp,
+            Ok(WebSocketResponse::Failure(_)) => continue,
+            // If the handshake's messages were skipped, the engine sends them again after
+            // the next reconnect, so just keep going.
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        // If any step of the handshake fails, there's no video until the next connection.
+        match event {
+            OkWebSocketResponseData::IceServerInfo { ice_servers } => {
+                if let Some(old) = peer.take() {
+                    let _ = old.close().await;
+                }
+                early_candidates.clear();
+                peer = offer(ice_servers, &actor_tx, &frames).await.ok();
+            }
+            OkWebSocketResponseData::SdpAnswer { answer } => {
+                if let (Some(peer), Ok(answer)) = (&peer, RTCSessionDescription::try_from(*answer)) {
+                    if peer.set_remote_description(answer).await.is_ok() {
+                        for candidate in early_candidates.drain(..) {
+                            let _ = peer.add_ice_candidate(candidate).await;
+                        }
+                    }
+                }
+            }
+            OkWebSocketResponseData::TrickleIce { candidate } => {
+                if let Some(peer) = &peer {
+                    if peer.remote_description().await.is_some() {
+                        let _ = peer.add_ice_candidate((*candidate).into()).await;
+                    } else {
+                        early_candidates.push((*candidate).into());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(peer) = peer {
+        let _ = peer.close().await;
+    }
+}
+
+/// Open a peer connection which receives video, and offer it to the engine.
+async fn offer(
+    ice_servers: Vec<IceServer>,
+    actor_tx: &mpsc::WeakSender<Request>,
+    frames: &broadcast::Sender<VideoFrame>,
+) -> webrtc::error::Result<Arc<RTCPeerConnection>> {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+    let api = APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .build();
+    let config = RTCConfiguration {
+        ice_servers: ice_servers
+            .into_iter()
+            .map(|server| RTCIceServer {
+                urls: server.urls,
+                username: server.username.unwrap_or_default(),
+                credential: server.credential.unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let peer = Arc::new(api.new_peer_connection(config).await?);
+    peer.add_transceiver_from_kind(
+        RTPCodecType::Video,
+        Some(RTCRtpTransceiverInit {
+            direction: RTCRtpTransceiverDirection::Recvonly,
+            send_encodings: Vec::new(),
+        }),
+    )
+    .await?;
+
+    let candidates_tx = actor_tx.clone();
+    peer.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+        let actor_tx = candidates_tx.clone();
+        Box::pin(async move {
+            // No candidate means ICE gathering has finished.
+            let Some(Ok(candidate)) = candidate.map(|candidate| candidate.to_json()) else {
+                return;
+            };
+            send(
+                &actor_tx,
+                WebSocketRequest::TrickleIce {
+                    candidate: Box::new(candidate.into()),
+                },
+            )
+            .await;
+        })
+    }));
+    let frames = frames.clone();
+    peer.on_track(Box::new(move |track, _receiver, _transceiver| {
+        let frames = frames.clone();
+        Box::pin(async move {
+            if track.kind() == RTPCodecType::Video {
+                tokio::task::spawn(read_frames(track, frames));
+            }
+        })
+    }));
+
+    let offer = peer.create_offer(None).await?;
+    peer.set_local_description(offer.clone()).await?;
+    send(
+        actor_tx,
+        WebSocketRequest::SdpOffer {
+            offer: Box::new(offer.into()),
+        },
+    )
+    .await;
+    Ok(peer)
+}
+
+/// Reassemble the track's RTP packets into frames, until the track ends.
+async fn read_frames(track: Arc<TrackRemote>, frames: broadcast::Sender<VideoFrame>) {
+    let clock_rate = track.codec().capability.clock_rate;
+    let mut builder = SampleBuilder::new(MAX_LATE_PACKETS, H264Packet::default(), clock_rate);
+    while let Ok((packet, _)) = track.read_rtp().await {
+        builder.push(packet);
+        while let Some(sample) = builder.pop() {
+            let frame = VideoFrame {
+                is_keyframe: is_keyframe(&sample.data),
+                data: sample.data.to_vec(),
+                timestamp: sample.timestamp,
+                duration: sample.duration,
+            };
+            // If the send fails, nobody is watching the video right now, which is fine.
+            let _ = frames.send(frame);
+        }
+    }
+}
+
+/// Does this Annex B H.264 frame contain an IDR slice, i.e. can it be decoded on its own?
+fn is_keyframe(data: &[u8]) -> bool {
+    const IDR_SLICE: u8 = 5;
+    data.windows(4).any(|w| w[..3] == [0, 0, 1] && w[3] & 0x1f == IDR_SLICE)
+}
+
+/// Send a request to the engine, unless the session has already ended.
+async fn send(actor_tx: &mpsc::WeakSender<Request>, req: WebSocketRequest) {
+    if let Some(actor_tx) = actor_tx.upgrade() {
+        let _ = actor_tx.send(Request::Send(req)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes() {
+        // An SPS, a PPS and then an IDR slice, after 4 and 3 byte start codes.
+        let idr = [0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xce, 0, 0, 1, 0x65, 0x88];
+        assert!(is_keyframe(&idr));
+        // A slice which isn't IDR.
+        assert!(!is_keyframe(&[0, 0, 0, 1, 0x41, 0x9a]));
+        // Bytes which look like an IDR slice's header, but don't come after a start code.
+        assert!(!is_keyframe(&[0x65, 0x65, 0x65, 0x65]));
+        assert!(!is_keyframe(&[]));
+    }
+}
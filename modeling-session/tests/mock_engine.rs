@@ -0,0 +1,765 @@
+//! Run sessions against a local mock engine, instead of the real KittyCAD API.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use kittycad_mock_engine::{MockEngine, Reply};
+use kittycad_modeling_cmds::{
+    format::OutputFormat3d,
+    id::ModelingCmdId,
+    length_unit::LengthUnit,
+    ok_response::{output, OkModelingCmdResponse},
+    session::EngineParams,
+    shared::PostEffectType,
+    websocket::{ErrorCode, ModelingCmdReq, OkWebSocketResponseData, RawFile, WebSocketRequest, WebSocketResponse},
+    BooleanUnion, ClosePath, GetNumObjects, ModelingCmd, ModelingCmdEndpoint, ObjectVisible, StartPath,
+};
+use kittycad_modeling_session::{
+    BatchPolicy, CommandOutcome, Journal, JournalMessage, Metrics, ReconnectPolicy, RetryPolicy, RunCommandError,
+    Session, SessionBuilder, SessionPool,
+};
+use uuid::Uuid;
+
+fn builder(engine: &MockEngine) -> SessionBuilder {
+    let mut client = kittycad::Client::new("not a real token");
+    client.set_base_url(engine.base_url());
+    SessionBuilder {
+        client,
+        fps: None,
+        unlocked_framerate: None,
+        video_res_height: None,
+        video_res_width: None,
+        buffer_reqs: None,
+        await_response_timeout: Some(Duration::from_secs(1)),
+        show_grid: None,
+        heartbeat_interval: None,
+        reconnect: None,
+        post_effect: None,
+        pool: None,
+        replay: None,
+        api_call_id: None,
+        journal: None,
+        webrtc: None,
+        header_auth_token: None,
+        metrics: None,
+        retry: None,
+        auto_batch: None,
+        validate_cmds: None,
+    }
+}
+
+async fn start_session(engine: &MockEngine) -> Session {
+    Session::start(builder(engine)).await.unwrap()
+}
+
+/// Wait until the condition is true, failing the test if it takes too long.
+async fn eventually(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("condition should have become true");
+}
+
+fn random_id() -> ModelingCmdId {
+    Uuid::new_v4().into()
+}
+
+fn start_path() -> ModelingCmd {
+    ModelingCmd::StartPath(StartPath::default())
+}
+
+#[tokio::test]
+async fn run_command() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    let cmd_id = random_id();
+    let resp = session.run_command(cmd_id, start_path()).await.unwrap();
+    assert!(matches!(resp, OkModelingCmdResponse::Empty));
+    let received = engine.received_cmds();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].cmd_id, cmd_id);
+    assert!(matches!(received[0].cmd, ModelingCmd::StartPath(_)));
+}
+
+#[tokio::test]
+async fn from_stream() {
+    let engine = MockEngine::start().await.unwrap();
+    let (ws, _) = tokio_tungstenite::connect_async(engine.ws_url()).await.unwrap();
+    let session = Session::from_stream(ws, None, None, None);
+    session.run_command(random_id(), start_path()).await.unwrap();
+    assert_eq!(engine.received_cmds().len(), 1);
+}
+
+#[tokio::test]
+async fn engine_params() {
+    let engine = MockEngine::start().await.unwrap();
+    let mut client = kittycad::Client::new("not a real token");
+    client.set_base_url(engine.base_url());
+    let params = EngineParams {
+        post_effect: Some(PostEffectType::Ssao),
+        pool: Some("gpu".to_owned()),
+        ..Default::default()
+    };
+    let builder = SessionBuilder::from_engine_params(client, params);
+    assert_eq!(builder.engine_params().pool.as_deref(), Some("gpu"));
+    let _session = Session::start(builder).await.unwrap();
+    let uri = &engine.connection_uris()[0];
+    assert!(uri.contains("pool=gpu"), "{uri}");
+    assert!(uri.contains("post_effect=ssao"), "{uri}");
+}
+
+#[cfg(not(feature = "webrtc"))]
+#[tokio::test]
+async fn webrtc_needs_feature() {
+    let engine = MockEngine::start().await.unwrap();
+    let result = Session::start(SessionBuilder {
+        webrtc: Some(true),
+        ..builder(&engine)
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(engine.connections(), 0);
+}
+
+/// The engine's answer and ICE candidates can arrive in any order, and the session still connects.
+#[cfg(feature = "webrtc")]
+#[tokio::test]
+async fn webrtc_handshake_out_of_order() {
+    use webrtc::{
+        api::{media_engine::MediaEngine, APIBuilder},
+        ice_transport::ice_candidate::RTCIceCandidate,
+        peer_connection::{
+            configuration::RTCConfiguration, peer_connection_state::RTCPeerConnectionState,
+            sdp::session_description::RTCSessionDescription,
+        },
+    };
+
+    let engine = MockEngine::start().await.unwrap();
+    let _session = Session::start(SessionBuilder {
+        webrtc: Some(true),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    // Sending the ICE servers starts the handshake.
+    engine.push_event(OkWebSocketResponseData::IceServerInfo {
+        ice_servers: Vec::new(),
+    });
+    let sent_offer = || {
+        engine.received().into_iter().find_map(|req| match req {
+            WebSocketRequest::SdpOffer { offer } => Some(*offer),
+            _ => None,
+        })
+    };
+    eventually(|| sent_offer().is_some()).await;
+
+    // Play the engine's part, with a peer connection of our own.
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs().unwrap();
+    let api = APIBuilder::new().with_media_engine(media_engine).build();
+    let engine_peer = api.new_peer_connection(RTCConfiguration::default()).await.unwrap();
+    let (candidates_tx, mut candidates_rx) = tokio::sync::mpsc::unbounded_channel();
+    engine_peer.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+        let _ = candidates_tx.send(candidate);
+        Box::pin(async {})
+    }));
+    let (state_tx, mut state_rx) = tokio::sync::mpsc::unbounded_channel();
+    engine_peer.on_peer_connection_state_change(Box::new(move |state: RTCPeerConnectionState| {
+        let _ = state_tx.send(state);
+        Box::pin(async {})
+    }));
+    let offer = RTCSessionDescription::try_from(sent_offer().unwrap()).unwrap();
+    engine_peer.set_remote_description(offer).await.unwrap();
+    let answer = engine_peer.create_answer(None).await.unwrap();
+    engine_peer.set_local_description(answer.clone()).await.unwrap();
+
+    // Send every candidate before the answer. This peer never learns the session's candidates,
+    // so they can only connect if the session holds on to these until it has the answer.
+    while let Some(Some(candidate)) = candidates_rx.recv().await {
+        engine.push_event(OkWebSocketResponseData::TrickleIce {
+            candidate: Box::new(candidate.to_json().unwrap().into()),
+        });
+    }
+    engine.push_event(OkWebSocketResponseData::SdpAnswer {
+        answer: Box::new(answer.into()),
+    });
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(state) = state_rx.recv().await {
+            if state == RTCPeerConnectionState::Connected {
+                return;
+            }
+        }
+        panic!("the peer connection closed without connecting");
+    })
+    .await
+    .expect("the session should have connected");
+    engine_peer.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn resolution_must_be_multiple_of_4() {
+    let engine = MockEngine::start().await.unwrap();
+    let result = Session::start(SessionBuilder {
+        video_res_width: Some(1281),
+        ..builder(&engine)
+    })
+    .await;
+    assert!(result.is_err());
+    // The session didn't even try to connect.
+    assert_eq!(engine.connections(), 0);
+}
+
+#[tokio::test]
+async fn pipelined_responses_can_arrive_out_of_order() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    // The first command's response arrives after the second's.
+    engine.queue_reply(Reply::default().after(Duration::from_millis(200)));
+    let first = session.send_command(random_id(), start_path()).await.unwrap();
+    let second = session.send_command(random_id(), start_path()).await.unwrap();
+    second.await.unwrap();
+    first.await.unwrap();
+}
+
+#[tokio::test]
+async fn engine_failure() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    engine.queue_reply(Reply::error(ErrorCode::BadRequest, "no thanks"));
+    let cmd_id = random_id();
+    let err = session.run_command(cmd_id, start_path()).await.unwrap_err();
+    let RunCommandError::ModelingApiFailure { request_id, errors } = err else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(request_id, Some(cmd_id.into()));
+    assert_eq!(errors[0].error_code, ErrorCode::BadRequest);
+}
+
+#[tokio::test]
+async fn typed_output() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    let output::StartPath {} = session.run(random_id(), StartPath::default()).await.unwrap();
+    // A response for some other command is the wrong type.
+    engine.queue_reply(OkModelingCmdResponse::ClosePath(output::ClosePath {
+        face_id: Uuid::new_v4(),
+    }));
+    let err = session.run(random_id(), StartPath::default()).await.unwrap_err();
+    assert!(matches!(err, RunCommandError::ServerSentWrongType));
+}
+
+#[tokio::test]
+async fn batch_stops_at_first_failure() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    engine.queue_reply(Reply::default());
+    engine.queue_reply(Reply::error(ErrorCode::BadRequest, "no thanks"));
+    let requests: Vec<_> = (0..3)
+        .map(|_| ModelingCmdReq {
+            cmd: start_path(),
+            cmd_id: random_id(),
+        })
+        .collect();
+    let results = session.run_batch(requests, random_id()).await.unwrap();
+    assert!(results[0].is_ok());
+    assert_eq!(results[1].as_ref().unwrap_err()[0].error_code, ErrorCode::BadRequest);
+    // The engine never tried the last command.
+    assert!(matches!(&results[2], Err(errors) if errors.is_empty()));
+}
+
+#[tokio::test]
+async fn timeout() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    engine.queue_reply(Reply::Ignore);
+    let err = session.run_command(random_id(), start_path()).await.unwrap_err();
+    assert!(matches!(err, RunCommandError::TimeOutWaitingForResponse));
+}
+
+#[tokio::test]
+async fn timeout_override() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    // Much slower than the session's usual timeout, but the override allows for it.
+    engine.queue_reply(Reply::default().after(Duration::from_millis(1500)));
+    session
+        .run_command_with_timeout(random_id(), start_path(), Duration::from_secs(3))
+        .await
+        .unwrap();
+    // And much faster.
+    engine.queue_reply(Reply::Ignore);
+    let err = session
+        .run_command_with_timeout(random_id(), start_path(), Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RunCommandError::TimeOutWaitingForResponse));
+}
+
+#[tokio::test]
+async fn disconnect_ends_session() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    engine.queue_reply(Reply::Disconnect);
+    let err = session.run_command(random_id(), start_path()).await.unwrap_err();
+    assert!(err.should_end_session(), "unexpected error {err}");
+}
+
+#[tokio::test]
+async fn reconnect_replays_journal() {
+    let engine = MockEngine::start().await.unwrap();
+    let policy = ReconnectPolicy {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let session = Session::start(SessionBuilder {
+        reconnect: Some(policy),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    let cmd_id = random_id();
+    session.run_command(cmd_id, start_path()).await.unwrap();
+
+    engine.disconnect_all();
+    // Wait for the session to reconnect and replay the command it already ran.
+    eventually(|| engine.received_cmds().len() >= 2).await;
+    assert_eq!(engine.connections(), 2);
+    let replayed = engine.received_cmds();
+    assert_eq!(replayed[1].cmd_id, cmd_id);
+
+    // The session still works over the new connection.
+    session.run_command(random_id(), start_path()).await.unwrap();
+}
+
+#[tokio::test]
+async fn reconnect_skips_queries() {
+    let engine = MockEngine::start().await.unwrap();
+    let policy = ReconnectPolicy {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let session = Session::start(SessionBuilder {
+        reconnect: Some(policy),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    let path_id = random_id();
+    session.run_command(path_id, start_path()).await.unwrap();
+    let query = ModelingCmd::GetNumObjects(GetNumObjects::default());
+    session.run_command(random_id(), query).await.unwrap();
+    let hide_id = random_id();
+    let hide = ObjectVisible::builder().object_id(path_id.0).hidden(true).build();
+    session
+        .run_command(hide_id, ModelingCmd::ObjectVisible(hide))
+        .await
+        .unwrap();
+
+    engine.disconnect_all();
+    eventually(|| engine.connections() == 2).await;
+    // The replay finishes before the session sends anything new.
+    let next_id = random_id();
+    session.run_command(next_id, start_path()).await.unwrap();
+    // The query didn't change the scene, so it wasn't replayed, but the path is hidden again.
+    let ids: Vec<_> = engine.received_cmds()[3..].iter().map(|req| req.cmd_id).collect();
+    assert_eq!(ids, [path_id, hide_id, next_id]);
+}
+
+#[tokio::test]
+async fn heartbeat() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = Session::start(SessionBuilder {
+        heartbeat_interval: Some(Duration::from_millis(50)),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(session.latency().is_some());
+    assert!(session.is_healthy());
+
+    // Once the engine stops answering pings, the session is unhealthy,
+    // so timeouts mean the session should end.
+    engine.respond_to_pings(false);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!session.is_healthy());
+    engine.queue_reply(Reply::Ignore);
+    let err = session.run_command(random_id(), start_path()).await.unwrap_err();
+    assert!(matches!(err, RunCommandError::EngineUnresponsive));
+    assert!(err.should_end_session());
+}
+
+#[tokio::test]
+async fn export_to_dir() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    engine.queue_reply(Reply::Export(vec![RawFile {
+        // The engine shouldn't be able to write outside the directory.
+        name: "../cube.stl".to_owned(),
+        contents: b"solid cube".to_vec(),
+    }]));
+    let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir(&dir).unwrap();
+    let paths = session
+        .export_to_dir(Vec::new(), OutputFormat3d::Stl(Default::default()), &dir)
+        .await
+        .unwrap();
+    assert_eq!(paths, vec![dir.join("cube.stl")]);
+    assert_eq!(std::fs::read(&paths[0]).unwrap(), b"solid cube");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn events() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = start_session(&engine).await;
+    let mut events = session.events();
+    engine.push_event(OkWebSocketResponseData::MetricsRequest {});
+    tokio::time::timeout(Duration::from_secs(1), async {
+        // Other events, like the session data, might arrive first.
+        while let Some(event) = events.next().await {
+            if let WebSocketResponse::Success(event) = event {
+                if matches!(event.resp, OkWebSocketResponseData::MetricsRequest {}) {
+                    return;
+                }
+            }
+        }
+    })
+    .await
+    .expect("should have received the metrics request");
+    // The session answers the engine's metrics requests by itself.
+    eventually(|| {
+        engine
+            .received()
+            .iter()
+            .any(|req| matches!(req, WebSocketRequest::MetricsResponse { .. }))
+    })
+    .await;
+    // The engine told the session which API call it's using, as soon as it connected.
+    assert_eq!(session.api_call_id().as_deref(), Some("mock-api-call-1"));
+}
+
+#[tokio::test]
+async fn pool_reuses_sessions() {
+    let engine = MockEngine::start().await.unwrap();
+    let pool = SessionPool::start(builder(&engine), 2).await.unwrap();
+    let first = pool.lease().await.unwrap();
+    let _second = pool.lease().await.unwrap();
+    // Both sessions are leased, so the next lease has to wait.
+    assert!(tokio::time::timeout(Duration::from_millis(50), pool.lease())
+        .await
+        .is_err());
+    first.run_command(random_id(), start_path()).await.unwrap();
+    drop(first);
+    let _third = pool.lease().await.unwrap();
+    // The session was reset between leases, rather than a new one being started.
+    assert_eq!(engine.connections(), 2);
+    assert!(engine
+        .received_cmds()
+        .iter()
+        .any(|req| matches!(req.cmd, ModelingCmd::SceneClearAll(_))));
+    let metrics = pool.metrics();
+    assert_eq!(metrics.leased, 2);
+    assert_eq!(metrics.leases, 3);
+    assert_eq!(metrics.evicted, 0);
+}
+
+#[tokio::test]
+async fn pool_evicts_unhealthy_sessions() {
+    let engine = MockEngine::start().await.unwrap();
+    let pool = SessionPool::start(builder(&engine), 1).await.unwrap();
+    let mut lease = pool.lease().await.unwrap();
+    lease.report(&RunCommandError::WebSocketClosed);
+    drop(lease);
+    // The pool starts a replacement.
+    let _lease = pool.lease().await.unwrap();
+    assert_eq!(engine.connections(), 2);
+    let metrics = pool.metrics();
+    assert_eq!(metrics.evicted, 1);
+    assert_eq!(metrics.started, 2);
+}
+
+#[tokio::test]
+async fn pool_evicts_idle_sessions_which_disconnected() {
+    let engine = MockEngine::start().await.unwrap();
+    let pool = SessionPool::start(builder(&engine), 1).await.unwrap();
+    engine.disconnect_all();
+    // Give the session time to notice its connection closed.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let lease = pool.lease().await.unwrap();
+    lease.run_command(random_id(), start_path()).await.unwrap();
+    assert_eq!(engine.connections(), 2);
+    let metrics = pool.metrics();
+    assert_eq!(metrics.evicted, 1);
+    assert_eq!(metrics.started, 2);
+}
+
+#[tokio::test]
+async fn pool_evicts_leases_dropped_outside_runtime() {
+    let engine = MockEngine::start().await.unwrap();
+    let pool = SessionPool::start(builder(&engine), 1).await.unwrap();
+    let lease = pool.lease().await.unwrap();
+    // There's no runtime on this thread to clear the session's scene with.
+    std::thread::spawn(move || drop(lease)).join().unwrap();
+    let metrics = pool.metrics();
+    assert_eq!(metrics.leased, 0);
+    assert_eq!(metrics.evicted, 1);
+    // The permit was released, so the pool can start a replacement.
+    let _lease = pool.lease().await.unwrap();
+    assert_eq!(engine.connections(), 2);
+}
+
+#[tokio::test]
+async fn pool_needs_sessions() {
+    let engine = MockEngine::start().await.unwrap();
+    assert!(SessionPool::start(builder(&engine), 0).await.is_err());
+}
+
+#[tokio::test]
+async fn journal_replay() {
+    let engine = MockEngine::start().await.unwrap();
+    let path = std::env::temp_dir().join(format!("{}.ndjson", Uuid::new_v4()));
+    let session = Session::start(SessionBuilder {
+        journal: Some(path.clone()),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    let cmd_id = random_id();
+    session.run_command(cmd_id, start_path()).await.unwrap();
+    drop(session);
+    // The journal is written in the background, so wait for the response to be written.
+    let read_journal = || Journal::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    eventually(|| {
+        read_journal().entries.iter().any(|entry| {
+            matches!(&entry.message, JournalMessage::Received(resp) if resp.request_id() == Some(cmd_id.into()))
+        })
+    })
+    .await;
+    let journal = read_journal();
+    std::fs::remove_file(&path).unwrap();
+    assert!(journal.entries.iter().any(|entry| matches!(
+        &entry.message,
+        JournalMessage::Sent(WebSocketRequest::ModelingCmdReq(req)) if req.cmd_id == cmd_id
+    )));
+
+    // Replaying against an engine which behaves the same way finds no differences.
+    let session = start_session(&engine).await;
+    assert!(session.replay(&journal).await.unwrap().is_empty());
+
+    // But if the engine behaves differently, the replay finds where.
+    engine.handle(|_| Reply::error(ErrorCode::BadRequest, "no thanks"));
+    let mismatches = session.replay(&journal).await.unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].request_id, Uuid::from(cmd_id));
+    assert!(matches!(mismatches[0].replayed, Some(WebSocketResponse::Failure(_))));
+}
+
+#[tokio::test]
+async fn header_auth() {
+    let engine = MockEngine::start().await.unwrap();
+    engine.require_token("first");
+
+    // Without a token, the engine refuses to run anything.
+    let session = start_session(&engine).await;
+    let err = session.run_command(random_id(), start_path()).await.unwrap_err();
+    assert!(matches!(err, RunCommandError::AuthFailed { .. }), "{err}");
+
+    let session = Session::start(SessionBuilder {
+        header_auth_token: Some("first".to_owned()),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    session.run_command(random_id(), start_path()).await.unwrap();
+
+    // The token expires, so refresh it.
+    engine.require_token("second");
+    let err = session.run_command(random_id(), start_path()).await.unwrap_err();
+    assert!(matches!(err, RunCommandError::AuthFailed { .. }), "{err}");
+    session.refresh_token("second").await.unwrap();
+    session.run_command(random_id(), start_path()).await.unwrap();
+}
+
+/// Remembers every command which finished.
+#[derive(Default)]
+struct RecordedMetrics {
+    sent: Mutex<Vec<ModelingCmdEndpoint>>,
+    finished: Mutex<Vec<(ModelingCmdEndpoint, CommandOutcome)>>,
+}
+
+impl Metrics for RecordedMetrics {
+    fn command_sent(&self, endpoint: &ModelingCmdEndpoint, payload_bytes: usize) {
+        assert!(payload_bytes > 0);
+        self.sent.lock().unwrap().push(endpoint.clone());
+    }
+
+    fn command_finished(&self, endpoint: &ModelingCmdEndpoint, outcome: CommandOutcome, _latency: Duration) {
+        self.finished.lock().unwrap().push((endpoint.clone(), outcome));
+    }
+}
+
+#[tokio::test]
+async fn metrics() {
+    let engine = MockEngine::start().await.unwrap();
+    let metrics = Arc::new(RecordedMetrics::default());
+    let session = Session::start(SessionBuilder {
+        metrics: Some(metrics.clone()),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    session.run_command(random_id(), start_path()).await.unwrap();
+    engine.queue_reply(Reply::error(ErrorCode::BadRequest, "no thanks"));
+    session.run_command(random_id(), start_path()).await.unwrap_err();
+    // The second command in the batch is never tried.
+    engine.queue_reply(Reply::error(ErrorCode::BadRequest, "no thanks"));
+    let requests = vec![
+        ModelingCmdReq {
+            cmd: start_path(),
+            cmd_id: random_id(),
+        },
+        ModelingCmdReq {
+            cmd: ModelingCmd::SceneClearAll(Default::default()),
+            cmd_id: random_id(),
+        },
+    ];
+    session.run_batch(requests, random_id()).await.unwrap();
+    let requests = vec![ModelingCmdReq {
+        cmd: start_path(),
+        cmd_id: random_id(),
+    }];
+    session.run_batch_no_responses(requests, random_id()).await.unwrap();
+
+    assert_eq!(metrics.sent.lock().unwrap().len(), 5);
+    assert_eq!(
+        *metrics.finished.lock().unwrap(),
+        vec![
+            (ModelingCmdEndpoint::StartPath, CommandOutcome::Success),
+            (ModelingCmdEndpoint::StartPath, CommandOutcome::Failure),
+            (ModelingCmdEndpoint::StartPath, CommandOutcome::Failure),
+            (ModelingCmdEndpoint::SceneClearAll, CommandOutcome::Skipped),
+            (ModelingCmdEndpoint::StartPath, CommandOutcome::Sent),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn retry_read_only_cmds() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = Session::start(SessionBuilder {
+        retry: Some(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+
+    // Queries are retried if the engine had a temporary problem.
+    engine.queue_reply(Reply::error(ErrorCode::InternalEngine, "try again"));
+    let query = ModelingCmd::GetNumObjects(Default::default());
+    let query_id = random_id();
+    session.run_command(query_id, query.clone()).await.unwrap();
+    let received = engine.received_cmds();
+    assert_eq!(received.len(), 2);
+    // The retry can't reuse the first attempt's ID.
+    assert_eq!(received[0].cmd_id, query_id);
+    assert_ne!(received[1].cmd_id, query_id);
+
+    // But not if the request was wrong.
+    engine.queue_reply(Reply::error(ErrorCode::BadRequest, "no thanks"));
+    session.run_command(random_id(), query).await.unwrap_err();
+    assert_eq!(engine.received_cmds().len(), 3);
+
+    // And commands which change the scene are never retried.
+    engine.queue_reply(Reply::error(ErrorCode::InternalEngine, "try again"));
+    session.run_command(random_id(), start_path()).await.unwrap_err();
+    assert_eq!(engine.received_cmds().len(), 4);
+}
+
+#[tokio::test]
+async fn auto_batch() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = Session::start(SessionBuilder {
+        auto_batch: Some(BatchPolicy {
+            max_cmds: 2,
+            max_delay: Duration::from_millis(100),
+        }),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    let close_path = || ModelingCmd::ClosePath(ClosePath::builder().path_id(Uuid::new_v4()).build());
+
+    // The first two commands fill a batch, and the third is sent before the command which isn't safe to batch.
+    engine.queue_reply(Reply::default());
+    engine.queue_reply(Reply::error(ErrorCode::BadRequest, "no thanks"));
+    let mut pending = Vec::new();
+    for _ in 0..3 {
+        pending.push(session.send_command(random_id(), close_path()).await.unwrap());
+    }
+    session.run_command(random_id(), start_path()).await.unwrap();
+    // Each command still gets its own response.
+    let mut results = Vec::new();
+    for pending in pending {
+        results.push(pending.await);
+    }
+    assert!(results[0].is_ok());
+    let Err(RunCommandError::ModelingApiFailure { errors, .. }) = &results[1] else {
+        panic!("unexpected result {:?}", results[1]);
+    };
+    assert_eq!(errors[0].error_code, ErrorCode::BadRequest);
+    assert!(results[2].is_ok());
+
+    // A command on its own is sent once it's waited long enough.
+    session.run_command(random_id(), close_path()).await.unwrap();
+
+    let sent: Vec<_> = engine
+        .received()
+        .into_iter()
+        .filter_map(|req| match req {
+            WebSocketRequest::ModelingCmdBatchReq(batch) => Some(batch.requests.len()),
+            WebSocketRequest::ModelingCmdReq(_) => Some(0),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sent, vec![2, 1, 0, 1]);
+}
+
+#[tokio::test]
+async fn validate_cmds() {
+    let engine = MockEngine::start().await.unwrap();
+    let session = Session::start(SessionBuilder {
+        validate_cmds: Some(true),
+        ..builder(&engine)
+    })
+    .await
+    .unwrap();
+    let cmd_id = random_id();
+    let union = BooleanUnion::builder()
+        .solid_ids(Vec::new())
+        .tolerance(LengthUnit(0.1))
+        .build();
+    let err = session
+        .run_command(cmd_id, ModelingCmd::BooleanUnion(union))
+        .await
+        .unwrap_err();
+    let RunCommandError::InvalidCommand { cmd_id: id, errors } = err else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(id, Uuid::from(cmd_id));
+    assert_eq!(errors[0].path, "solid_ids");
+    // The engine never saw it.
+    assert!(engine.received_cmds().is_empty());
+    // Valid commands are still sent.
+    session.run_command(random_id(), start_path()).await.unwrap();
+    assert_eq!(engine.received_cmds().len(), 1);
+}